@@ -2,6 +2,8 @@ extern crate winres;
 use std::env;
 
 fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+
     if cfg!(target_os = "windows") {
         let mut res = winres::WindowsResource::new();
         let exe = env::current_exe().unwrap();
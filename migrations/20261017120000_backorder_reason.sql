@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE Sale ADD COLUMN backorder_reason TEXT;
+ALTER TABLE Manufacture ADD COLUMN backorder_reason TEXT;
@@ -0,0 +1,9 @@
+-- Add migration script here
+CREATE TABLE ManufacturePart (
+    id INTEGER PRIMARY KEY,
+    qty INTEGER NOT NULL,
+    part_id INTEGER NOT NULL,
+    manufacture_id INTEGER NOT NULL,
+    FOREIGN KEY (part_id) REFERENCES Part (part_id) ON DELETE CASCADE,
+    FOREIGN KEY (manufacture_id) REFERENCES Manufacture (id) ON DELETE CASCADE
+);
@@ -0,0 +1,15 @@
+-- Add migration script here
+CREATE TABLE Setting (
+    id INTEGER PRIMARY KEY,
+    effective_date TEXT NOT NULL,
+    shipping REAL NOT NULL,
+    free_shipping_threshold REAL NOT NULL,
+    carrier_cost REAL NOT NULL
+);
+
+INSERT INTO Setting (effective_date, shipping, free_shipping_threshold, carrier_cost)
+VALUES ('1970-01-01', 15.00, 500.00, 9.00);
+
+ALTER TABLE Sale ADD COLUMN setting_id INTEGER REFERENCES Setting (id);
+
+UPDATE Sale SET setting_id = (SELECT id FROM Setting);
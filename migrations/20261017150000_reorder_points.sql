@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE Product ADD COLUMN reorder_point INTEGER DEFAULT 25 NOT NULL;
+ALTER TABLE Product ADD COLUMN reorder_qty INTEGER;
+ALTER TABLE Part ADD COLUMN reorder_point INTEGER DEFAULT 25 NOT NULL;
+ALTER TABLE Part ADD COLUMN reorder_qty INTEGER;
@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE RepPayout (
+    id INTEGER PRIMARY KEY,
+    rep_id INTEGER NOT NULL,
+    date TEXT NOT NULL,
+    amount REAL NOT NULL,
+    FOREIGN KEY (rep_id) REFERENCES Rep (id) ON DELETE CASCADE
+);
+
+ALTER TABLE Sale ADD COLUMN rep_payout_id INTEGER REFERENCES RepPayout (id) ON DELETE SET NULL;
@@ -0,0 +1,7 @@
+-- Add migration script here
+CREATE TABLE Invoice (
+    invoice_number INTEGER PRIMARY KEY AUTOINCREMENT,
+    sale_id INTEGER UNIQUE,
+    issued TEXT NOT NULL,
+    FOREIGN KEY (sale_id) REFERENCES Sale (sale_id) ON DELETE SET NULL
+);
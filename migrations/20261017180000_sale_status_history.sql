@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE SaleStatusChange (
+    id INTEGER PRIMARY KEY,
+    sale_id INTEGER NOT NULL,
+    from_status TEXT,
+    to_status TEXT NOT NULL,
+    changed_at TEXT NOT NULL,
+    FOREIGN KEY (sale_id) REFERENCES Sale (sale_id) ON DELETE CASCADE
+);
+
+-- Existing sales start their history at the status they have now.
+INSERT INTO SaleStatusChange (sale_id, from_status, to_status, changed_at)
+SELECT sale_id, NULL, status, date FROM Sale;
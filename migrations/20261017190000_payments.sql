@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE Payment (
+    id INTEGER PRIMARY KEY,
+    sale_id INTEGER NOT NULL,
+    date TEXT NOT NULL,
+    amount REAL NOT NULL,
+    method TEXT NOT NULL,
+    reference TEXT,
+    FOREIGN KEY (sale_id) REFERENCES Sale (sale_id) ON DELETE CASCADE
+);
+
+ALTER TABLE Setting ADD COLUMN payment_terms INTEGER NOT NULL DEFAULT 30;
@@ -0,0 +1,23 @@
+-- Add migration script here
+CREATE TABLE SaleReturn (
+    id INTEGER PRIMARY KEY,
+    sale_id INTEGER NOT NULL,
+    date TEXT NOT NULL,
+    refund REAL NOT NULL,
+    restocked_cost REAL NOT NULL,
+    rep_cut_reduction REAL NOT NULL,
+    note TEXT,
+    FOREIGN KEY (sale_id) REFERENCES Sale (sale_id) ON DELETE CASCADE
+);
+
+CREATE TABLE SaleReturnProduct (
+    id INTEGER PRIMARY KEY,
+    return_id INTEGER NOT NULL,
+    product_id INTEGER NOT NULL,
+    qty INTEGER NOT NULL,
+    restocked BOOLEAN NOT NULL,
+    FOREIGN KEY (return_id) REFERENCES SaleReturn (id) ON DELETE CASCADE,
+    FOREIGN KEY (product_id) REFERENCES Product (product_id) ON DELETE CASCADE
+);
+
+ALTER TABLE Sale ADD COLUMN refunded REAL NOT NULL DEFAULT 0.00;
@@ -0,0 +1,6 @@
+-- Add migration script here
+ALTER TABLE Sale ADD COLUMN discount_percent BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE Sale ADD COLUMN discount_amount REAL NOT NULL DEFAULT 0.00;
+
+ALTER TABLE SaleProduct ADD COLUMN discount REAL NOT NULL DEFAULT 0.00;
+ALTER TABLE SaleProduct ADD COLUMN discount_percent BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,14 @@
+-- Add migration script here
+ALTER TABLE Sale ADD COLUMN rep_percentage INTEGER;
+
+-- The rate a sale was submitted with is whatever its stored cut works out to,
+-- counting back in what returns took off it.
+UPDATE Sale
+SET rep_percentage = CASE
+    WHEN rep_cut IS NOT NULL AND total - shipping > 0 THEN CAST(ROUND(
+        (rep_cut + IFNULL((SELECT SUM(rep_cut_reduction) FROM SaleReturn
+        WHERE SaleReturn.sale_id = Sale.sale_id), 0.0)) * 100.0 / (total - shipping)
+    ) AS INTEGER)
+    ELSE (SELECT percentage FROM Rep WHERE Rep.id = Sale.rep_id)
+END
+WHERE rep_id IS NOT NULL;
@@ -0,0 +1,9 @@
+-- Add migration script here
+ALTER TABLE ManufacturePart ADD COLUMN product_id INTEGER REFERENCES Product (product_id) ON DELETE CASCADE;
+
+-- A run with a single product used every part for that product.
+UPDATE ManufacturePart
+SET product_id = (SELECT product_id FROM ManufactureProduct
+    WHERE ManufactureProduct.manufacture_id = ManufacturePart.manufacture_id)
+WHERE (SELECT COUNT(*) FROM ManufactureProduct
+    WHERE ManufactureProduct.manufacture_id = ManufacturePart.manufacture_id) = 1;
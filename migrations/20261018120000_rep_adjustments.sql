@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE RepAdjustment (
+    id INTEGER PRIMARY KEY,
+    rep_id INTEGER NOT NULL,
+    sale_id INTEGER,
+    date TEXT NOT NULL,
+    amount REAL NOT NULL,
+    rep_payout_id INTEGER,
+    FOREIGN KEY (rep_id) REFERENCES Rep (id) ON DELETE CASCADE,
+    FOREIGN KEY (sale_id) REFERENCES Sale (sale_id) ON DELETE SET NULL,
+    FOREIGN KEY (rep_payout_id) REFERENCES RepPayout (id) ON DELETE SET NULL
+);
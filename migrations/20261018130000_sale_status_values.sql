@@ -0,0 +1,31 @@
+-- Add migration script here
+-- Statuses are read strictly, so every stored value is brought onto one of the
+-- known ones: case and stray spaces are ignored and anything unrecognised goes
+-- back to DRAFT. The column is added again to give it a quoted default and a
+-- check that keeps other values out.
+ALTER TABLE Sale RENAME COLUMN status TO old_status;
+
+ALTER TABLE Sale ADD COLUMN status TEXT NOT NULL DEFAULT 'DRAFT'
+    CHECK (status IN ('DRAFT', 'CONFIRMED', 'PAID', 'SHIPPED', 'COMPLETED', 'CANCELLED', 'RETURNED'));
+
+UPDATE Sale
+SET status = CASE
+    WHEN UPPER(TRIM(old_status)) IN ('DRAFT', 'CONFIRMED', 'PAID', 'SHIPPED', 'COMPLETED', 'CANCELLED', 'RETURNED')
+        THEN UPPER(TRIM(old_status))
+    ELSE 'DRAFT'
+END;
+
+ALTER TABLE Sale DROP COLUMN old_status;
+
+UPDATE SaleStatusChange
+SET to_status = CASE
+    WHEN UPPER(TRIM(to_status)) IN ('DRAFT', 'CONFIRMED', 'PAID', 'SHIPPED', 'COMPLETED', 'CANCELLED', 'RETURNED')
+        THEN UPPER(TRIM(to_status))
+    ELSE 'DRAFT'
+END,
+from_status = CASE
+    WHEN from_status IS NULL THEN NULL
+    WHEN UPPER(TRIM(from_status)) IN ('DRAFT', 'CONFIRMED', 'PAID', 'SHIPPED', 'COMPLETED', 'CANCELLED', 'RETURNED')
+        THEN UPPER(TRIM(from_status))
+    ELSE 'DRAFT'
+END;
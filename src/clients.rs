@@ -1,32 +1,22 @@
-use std::env;
-
 use iced::{
     alignment::Horizontal,
     widget::{Button, Column, Container, Row, Scrollable, Text},
     Alignment, Element, Length,
 };
-use sqlx::SqlitePool;
 
 use crate::{
     components::{
-        add_button, layout, table_column, table_header, table_row_style, table_style,
+        add_button, export_row, layout, table_column, table_header, table_row_style, table_style,
         text_input_column, CustomButtonStyle, CustomMainButtonStyle,
     },
-    error::Errorr,
+    repo::client::Client,
     AppMessage,
 };
 
-#[derive(Default, Clone, Debug)]
-pub struct Client {
-    pub client_id: i64,
-    pub name: String,
-    pub email: Option<String>,
-    pub address: String,
-}
-
 #[derive(Default, Clone)]
 pub struct ClientState {
     pub clients: Vec<Client>,
+    pub export_path: String,
     add_client: bool,
     pub client_to_add: Client,
     pub edit_client: bool,
@@ -41,96 +31,17 @@ pub enum ClientMessage {
     Submit(bool),
     ShowAddClient,
     Delete,
-}
-
-pub async fn get_clients() -> Result<Vec<Client>, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let clients = sqlx::query_as!(Client, "SELECT * FROM Client")
-        .fetch_all(&pool)
-        .await?;
-
-    Ok(clients)
-}
-
-pub async fn get_client(i: i64) -> Result<Client, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let client = sqlx::query_as!(Client, "SELECT * FROM Client WHERE client_id = ?", i)
-        .fetch_one(&pool)
-        .await?;
-
-    Ok(client)
-}
-
-pub async fn add_client(client: Client) -> Result<(), Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let name = client.name;
-    let address = client.address;
-    let email = client.email;
-
-    sqlx::query!(
-        "
-        INSERT INTO Client (name, address, email)
-        VALUES (?,?,?)
-        ",
-        name,
-        address,
-        email,
-    )
-    .execute(&pool)
-    .await?;
-
-    Ok(())
-}
-
-pub async fn edit_client(client: Client) -> Result<(), Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let id = client.client_id;
-    let name = client.name;
-    let address = client.address;
-    let email = client.email;
-
-    sqlx::query!(
-        "
-        UPDATE Client
-        SET name = ?, address = ?, email = ?
-        WHERE client_id = ?
-        ",
-        name,
-        address,
-        email,
-        id
-    )
-    .execute(&pool)
-    .await?;
-
-    Ok(())
-}
-
-pub async fn delete_client(client: Client) -> Result<(), Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let id = client.client_id;
-
-    sqlx::query!(
-        "
-        DELETE FROM Client
-        WHERE client_id = ?
-        ",
-        id
-    )
-    .execute(&pool)
-    .await?;
-
-    Ok(())
+    ExportPath(String),
+    Export,
 }
 
 impl ClientState {
     pub fn update(&mut self, message: ClientMessage) {
         match message {
+            ClientMessage::ExportPath(p) => {
+                self.export_path = p;
+            }
+            ClientMessage::Export => {}
             ClientMessage::NameInput(s, is_edit) => {
                 if is_edit {
                     self.client_to_edit.name = s;
@@ -254,6 +165,12 @@ impl ClientState {
                             "Add Cient",
                             AppMessage::Client(ClientMessage::ShowAddClient),
                         ))
+                        .push(export_row(
+                            &self.export_path,
+                            |input| AppMessage::Client(ClientMessage::ExportPath(input)),
+                            AppMessage::Client(ClientMessage::Export),
+                        ))
+                        .spacing(12)
                         .padding(12),
                 )
                 .push_maybe(self.create_view())
@@ -3,11 +3,11 @@ use std::env;
 use iced::{
     alignment::Horizontal,
     font::Weight,
-    widget::{button, container, svg, Button, Column, Container, Row, Text, TextInput},
+    widget::{button, container, svg, Button, Checkbox, Column, Container, Row, Text, TextInput},
     Alignment, Background, Border, Color, Element, Font, Length, Vector,
 };
 
-use crate::AppMessage;
+use crate::{repo::Shortage, AppMessage};
 
 const MAIN_COLOR: Color = Color {
     r: 0.5,
@@ -37,6 +37,20 @@ const SHADOW_COLOR: Color = Color {
     a: 0.9,
 };
 
+const SUCCESS_COLOR: Color = Color {
+    r: 0.2,
+    g: 0.6,
+    b: 0.3,
+    a: 1.0,
+};
+
+const ERROR_COLOR: Color = Color {
+    r: 0.8,
+    g: 0.2,
+    b: 0.2,
+    a: 1.0,
+};
+
 const TEXT_COLOR: Color = Color {
     r: 0.3,
     g: 0.3,
@@ -51,6 +65,10 @@ pub fn bold_text(s: &str) -> Text {
     })
 }
 
+pub fn error_text(s: &str) -> Text<'_> {
+    Text::new(s.to_string()).style(ERROR_COLOR)
+}
+
 pub fn text_input_column<'a, F>(
     label: &'static str,
     value: &'a str,
@@ -72,6 +90,27 @@ where
         })
 }
 
+// Path input and button for writing the current table out as CSV.
+pub fn export_row<'a, F>(path: &'a str, on_input: F, on_export: AppMessage) -> Row<'a, AppMessage>
+where
+    F: 'a + Fn(String) -> AppMessage,
+{
+    Row::new()
+        .spacing(8)
+        .align_items(Alignment::Center)
+        .push(
+            TextInput::new("Export to (path.csv)", path)
+                .on_input(on_input)
+                .on_submit(on_export.clone())
+                .width(300),
+        )
+        .push(
+            Button::new("Export CSV")
+                .on_press(on_export)
+                .style(CustomMainButtonStyle),
+        )
+}
+
 pub struct CustomButtonStyle;
 
 impl button::StyleSheet for CustomButtonStyle {
@@ -187,6 +226,10 @@ fn navbar() -> Container<'static, AppMessage> {
     let sale = svg_handle("sale");
     let client = svg_handle("client");
     let rep = svg_handle("rep");
+    let payments = svg_handle("payments");
+    let report = svg_handle("report");
+    let import = svg_handle("import");
+    let settings = svg_handle("settings");
 
     Container::new(
         Column::new()
@@ -207,7 +250,19 @@ fn navbar() -> Container<'static, AppMessage> {
             .push(navbar_button(product, "Products", AppMessage::GoToProducts))
             .push(navbar_button(sale, "Sales", AppMessage::GoToSales))
             .push(navbar_button(client, "Clients", AppMessage::GoToClients))
-            .push(navbar_button(rep, "Reps", AppMessage::GoToReps)),
+            .push(navbar_button(rep, "Reps", AppMessage::GoToReps))
+            .push(navbar_button(
+                payments,
+                "Receivables",
+                AppMessage::GoToReceivables,
+            ))
+            .push(navbar_button(report, "Reports", AppMessage::GoToReports))
+            .push(navbar_button(import, "Import", AppMessage::GoToImport))
+            .push(navbar_button(
+                settings,
+                "Settings",
+                AppMessage::GoToSettings,
+            )),
     )
     .style(container::Appearance {
         background: Some(Background::Color(Color::WHITE)),
@@ -243,6 +298,97 @@ pub fn layout(content: Element<AppMessage>) -> Container<AppMessage> {
     .into()
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotificationKind {
+    Success,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub id: usize,
+    pub kind: NotificationKind,
+    pub message: String,
+}
+
+fn notification_banner(notification: &Notification) -> Container<'static, AppMessage> {
+    let color = match notification.kind {
+        NotificationKind::Success => SUCCESS_COLOR,
+        NotificationKind::Error => ERROR_COLOR,
+    };
+
+    Container::new(
+        Row::new()
+            .spacing(12)
+            .align_items(Alignment::Center)
+            .push(Text::new(notification.message.clone()).width(Length::Fill))
+            .push(
+                Button::new(Text::new("X").style(Color::WHITE))
+                    .on_press(AppMessage::DismissNotification(notification.id))
+                    .style(CustomButtonStyle),
+            ),
+    )
+    .padding([4, 12])
+    .width(Length::Fill)
+    .style(container::Appearance {
+        background: Some(Background::Color(color)),
+        text_color: Some(Color::WHITE),
+        border: Border {
+            color: Color::TRANSPARENT,
+            width: 1.0,
+            radius: 4.0.into(),
+        },
+        ..Default::default()
+    })
+}
+
+// Stacks the pending notifications above the current screen, newest first.
+pub fn with_notifications<'a>(
+    content: Element<'a, AppMessage>,
+    notifications: &[Notification],
+) -> Element<'a, AppMessage> {
+    if notifications.is_empty() {
+        return content;
+    }
+
+    let banners = notifications
+        .iter()
+        .rev()
+        .fold(Column::new().spacing(4).padding(12), |col, n| {
+            col.push(notification_banner(n))
+        });
+
+    Column::new()
+        .push(Container::new(banners).style(container::Appearance {
+            background: Some(Background::Color(BG_COLOR)),
+            ..Default::default()
+        }))
+        .push(content)
+        .into()
+}
+
+pub fn migration_error_view(message: &str) -> Element<'static, AppMessage> {
+    Container::new(
+        Container::new(
+            Column::new()
+                .spacing(8)
+                .push(bold_text("The database could not be updated"))
+                .push(Text::new(message.to_string())),
+        )
+        .padding(20)
+        .style(card_style()),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .center_x()
+    .center_y()
+    .style(container::Appearance {
+        background: Some(Background::Color(BG_COLOR)),
+        ..Default::default()
+    })
+    .into()
+}
+
 pub fn card_style() -> container::Appearance {
     container::Appearance {
         background: Some(Background::Color(Color::WHITE)),
@@ -283,12 +429,14 @@ pub fn table_row_style() -> container::Appearance {
     }
 }
 
-pub fn table_row_qty_style(qty: i64) -> container::Appearance {
+// Yellow at or under the reorder point, red once under 40% of it (10 of the
+// old fixed 25).
+pub fn table_row_qty_style(qty: i64, reorder_point: i64) -> container::Appearance {
     container::Appearance {
         text_color: Some(Color::BLACK),
-        background: if qty < 10 {
+        background: if qty * 5 < reorder_point * 2 {
             Some(Background::Color(Color::new(1.0, 0.0, 0.0, 1.0)))
-        } else if qty > 10 && qty < 25 {
+        } else if qty <= reorder_point {
             Some(Background::Color(Color::new(1.0, 1.0, 0.0, 1.0)))
         } else {
             Some(Background::Color(Color::WHITE))
@@ -354,3 +502,51 @@ pub fn close_edit_row(close: AppMessage, edit: AppMessage) -> Row<'static, AppMe
                 )
             )
 }
+
+// Lists what would go negative and offers the backorder override, whose reason
+// input only shows once the override is ticked.
+pub fn shortage_view<F, G>(
+    shortages: &[Shortage],
+    allow_backorder: bool,
+    reason: &str,
+    on_toggle: F,
+    on_reason: G,
+) -> Option<Element<'static, AppMessage>>
+where
+    F: 'static + Fn(bool) -> AppMessage,
+    G: 'static + Fn(String) -> AppMessage,
+{
+    if shortages.is_empty() {
+        return None;
+    }
+
+    Some(
+        Container::new(
+            Column::new()
+                .spacing(8)
+                .push(bold_text("Not enough stock").style(ERROR_COLOR))
+                .extend(shortages.iter().map(|s| {
+                    Text::new(format!(
+                        "{}: need {}, have {}",
+                        s.name, s.needed, s.available
+                    ))
+                    .into()
+                }))
+                .push(Checkbox::new("Allow backorder", allow_backorder).on_toggle(on_toggle))
+                .push_maybe(if allow_backorder {
+                    Some(text_input_column(
+                        "Backorder Reason",
+                        reason,
+                        on_reason,
+                        None,
+                    ))
+                } else {
+                    None
+                }),
+        )
+        .padding(12)
+        .width(Length::Fill)
+        .style(card_style())
+        .into(),
+    )
+}
@@ -1,22 +1,74 @@
-use std::env::VarError;
+use std::{env::VarError, fmt};
+
+use sqlx::error::ErrorKind;
 
 #[derive(Clone, Debug)]
 pub enum Errorr {
-    ApiError,
+    NotFound(String),
+    ConstraintViolation(String),
+    InsufficientStock(String),
+    InvalidInput(String),
+    ConfigMissing(String),
+    Io(String),
+    Database(String),
+    Migration(String),
+}
+
+impl Errorr {
+    pub fn message(&self) -> &str {
+        match self {
+            Errorr::NotFound(m)
+            | Errorr::ConstraintViolation(m)
+            | Errorr::InsufficientStock(m)
+            | Errorr::InvalidInput(m)
+            | Errorr::ConfigMissing(m)
+            | Errorr::Io(m)
+            | Errorr::Database(m)
+            | Errorr::Migration(m) => m,
+        }
+    }
+}
+
+impl fmt::Display for Errorr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
 }
 
 impl From<sqlx::Error> for Errorr {
     fn from(error: sqlx::Error) -> Errorr {
-        dbg!(error);
+        match error {
+            sqlx::Error::RowNotFound => Errorr::NotFound(String::from("Record not found")),
+            sqlx::Error::Database(e) => match e.kind() {
+                ErrorKind::ForeignKeyViolation => Errorr::ConstraintViolation(String::from(
+                    "This record is still referenced by other records",
+                )),
+                ErrorKind::UniqueViolation
+                | ErrorKind::NotNullViolation
+                | ErrorKind::CheckViolation => Errorr::ConstraintViolation(e.message().to_string()),
+                _ => Errorr::Database(e.message().to_string()),
+            },
+            sqlx::Error::Io(e) => Errorr::Io(e.to_string()),
+            sqlx::Error::Configuration(e) => Errorr::ConfigMissing(e.to_string()),
+            e => Errorr::Database(e.to_string()),
+        }
+    }
+}
 
-        Errorr::ApiError
+impl From<sqlx::migrate::MigrateError> for Errorr {
+    fn from(error: sqlx::migrate::MigrateError) -> Errorr {
+        Errorr::Migration(error.to_string())
     }
 }
 
 impl From<VarError> for Errorr {
     fn from(error: VarError) -> Errorr {
-        dbg!(error);
+        Errorr::ConfigMissing(error.to_string())
+    }
+}
 
-        Errorr::ApiError
+impl From<std::io::Error> for Errorr {
+    fn from(error: std::io::Error) -> Errorr {
+        Errorr::Io(error.to_string())
     }
 }
@@ -1,34 +1,109 @@
-use crate::{components::layout, error::Errorr, parts::Part, product::Product, AppMessage};
+use crate::{
+    components::{
+        card_style, layout, table_column, table_header, table_row_style, table_style,
+        CustomButtonStyle,
+    },
+    error::Errorr,
+    repo::{
+        part::{get_low_parts, Part},
+        product::{get_low_products, Product},
+        sale::{get_month_to_date, get_open_sales, SaleH, SalesSummary},
+    },
+    AppMessage,
+};
 use sqlx::SqlitePool;
-use std::env;
 
 use iced::{
     border::Radius,
-    widget::{container, Column, Container, Row, Text},
+    widget::{container, Button, Column, Container, Row, Scrollable, Text},
     Alignment, Background, Border, Color, Element, Length, Vector,
 };
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum HomeList {
+    Sales,
+    Products,
+    Parts,
+}
+
 #[derive(Default, Clone)]
 pub struct HomeState {
     pub sales: Vec<SaleH>,
     pub products: Vec<Product>,
     pub parts: Vec<Part>,
+    pub month_to_date: SalesSummary,
+    open_list: Option<HomeList>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum HomeMessage {}
+#[derive(Clone, Debug)]
+pub enum HomeMessage {
+    ToggleList(HomeList),
+    OpenSale(i64),
+    OpenProduct(Product),
+    OpenPart(Part),
+}
 
-fn alert_view(len: usize, text: String) -> Option<Element<'static, AppMessage>> {
+fn alert_view(len: usize, text: String, list: HomeList) -> Option<Element<'static, AppMessage>> {
     if len > 0 {
         Some(
-            card(Container::new(Column::new().push(Row::new().push(Text::new(text)))).into())
-                .into(),
+            Button::new(card(
+                Container::new(Column::new().push(Row::new().push(Text::new(text)))).into(),
+            ))
+            .padding(0)
+            .style(CustomButtonStyle)
+            .on_press(AppMessage::Home(HomeMessage::ToggleList(list)))
+            .into(),
         )
     } else {
         None
     }
 }
 
+fn kpi_view(label: &str, value: String) -> Container<'static, AppMessage> {
+    Container::new(
+        Column::new()
+            .align_items(Alignment::Center)
+            .push(Text::new(label.to_string()))
+            .push(Text::new(value).size(24)),
+    )
+    .padding(12)
+    .width(180)
+    .style(card_style())
+}
+
+fn list_view<'a>(
+    headers: &[&str],
+    rows: impl Iterator<Item = (Vec<String>, AppMessage)>,
+) -> Element<'a, AppMessage> {
+    Container::new(
+        table_header(headers).push(Scrollable::new(Column::new().extend(rows.map(
+            |(columns, msg)| {
+                Button::new(
+                    Container::new(
+                        Row::new()
+                            .padding(10)
+                            .extend(columns.iter().map(|c| table_column(c).into())),
+                    )
+                    .style(table_row_style()),
+                )
+                .style(CustomButtonStyle)
+                .on_press(msg)
+                .into()
+            },
+        )))),
+    )
+    .max_height(300)
+    .style(table_style())
+    .into()
+}
+
+// A part short of its reorder point gets topped up by at least its reorder
+// quantity.
+fn suggested_purchase(part: &Part) -> i64 {
+    let shortfall = part.reorder_point - part.units_left;
+    shortfall.max(part.reorder_qty.unwrap_or(0))
+}
+
 fn card(view: Element<AppMessage>) -> Container<AppMessage> {
     Container::new(Column::new().width(Length::Fill).padding(8).push(view)).style(
         container::Appearance {
@@ -53,62 +128,23 @@ pub struct SPS {
     pub sales: Vec<SaleH>,
     pub products: Vec<Product>,
     pub parts: Vec<Part>,
+    pub month_to_date: SalesSummary,
 }
 
-#[derive(Clone, Debug)]
-pub struct SaleH {
-    pub sale_id: i64,
-    pub discount: Option<f64>,
-    pub total: f64,
-    pub cost: f64,
-    pub net: f64,
-    pub date: String,
-    pub client_id: i64,
-    pub rep_cut: Option<f64>,
-    pub status: String,
-    pub shipping: f64,
-    pub rep_id: Option<i64>,
-    pub note: Option<String>,
-}
+pub async fn get_home(pool: SqlitePool) -> Result<SPS, Errorr> {
+    let sales = get_open_sales(pool.clone()).await?;
 
-pub async fn get_home() -> Result<SPS, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
+    let products = get_low_products(pool.clone()).await?;
 
-    let sales = sqlx::query_as!(
-        SaleH,
-        "
-        SELECT * FROM Sale
-        WHERE status = ?
-        ",
-        "DRAFT"
-    )
-    .fetch_all(&pool)
-    .await?;
-
-    let products = sqlx::query_as!(
-        Product,
-        "
-        SELECT * FROM Product
-        WHERE units <= 25
-        "
-    )
-    .fetch_all(&pool)
-    .await?;
-
-    let parts = sqlx::query_as!(
-        Part,
-        "
-        SELECT * FROM Part
-        WHERE units_left <= 25
-        "
-    )
-    .fetch_all(&pool)
-    .await?;
+    let parts = get_low_parts(pool.clone()).await?;
+
+    let month_to_date = get_month_to_date(pool).await?;
 
     let r = SPS {
         sales,
         products,
         parts,
+        month_to_date,
     };
 
     Ok(r)
@@ -116,7 +152,65 @@ pub async fn get_home() -> Result<SPS, Errorr> {
 
 impl HomeState {
     pub fn update(&mut self, message: HomeMessage) {
-        match message {}
+        match message {
+            HomeMessage::ToggleList(list) => {
+                if self.open_list.as_ref() == Some(&list) {
+                    self.open_list = None;
+                } else {
+                    self.open_list = Some(list);
+                }
+            }
+            HomeMessage::OpenSale(_) | HomeMessage::OpenProduct(_) | HomeMessage::OpenPart(_) => {
+                self.open_list = None;
+            }
+        }
+    }
+
+    fn open_list_view(&self) -> Option<Element<'_, AppMessage>> {
+        match self.open_list {
+            Some(HomeList::Sales) => Some(list_view(
+                &["Sale", "Date", "Total"],
+                self.sales.iter().map(|sale| {
+                    (
+                        vec![
+                            format!("#{}", sale.sale_id),
+                            sale.date.clone(),
+                            format!("${:.2}", sale.total),
+                        ],
+                        AppMessage::Home(HomeMessage::OpenSale(sale.sale_id)),
+                    )
+                }),
+            )),
+            Some(HomeList::Products) => Some(list_view(
+                &["Name", "Units", "Reorder At", "Shortfall"],
+                self.products.iter().map(|product| {
+                    (
+                        vec![
+                            product.name.clone(),
+                            product.units.to_string(),
+                            product.reorder_point.to_string(),
+                            (product.reorder_point - product.units).to_string(),
+                        ],
+                        AppMessage::Home(HomeMessage::OpenProduct(product.clone())),
+                    )
+                }),
+            )),
+            Some(HomeList::Parts) => Some(list_view(
+                &["Name", "Units Left", "Reorder At", "Buy"],
+                self.parts.iter().map(|part| {
+                    (
+                        vec![
+                            part.name.clone(),
+                            part.units_left.to_string(),
+                            part.reorder_point.to_string(),
+                            suggested_purchase(part).to_string(),
+                        ],
+                        AppMessage::Home(HomeMessage::OpenPart(part.clone())),
+                    )
+                }),
+            )),
+            None => None,
+        }
     }
 
     pub fn view(&self) -> Element<AppMessage> {
@@ -129,12 +223,31 @@ impl HomeState {
                         .padding([0, 0, 12, 0])
                         .push(Text::new("Home".to_string()).size(24)),
                 )
+                .push(
+                    Row::new()
+                        .spacing(12)
+                        .padding([0, 0, 12, 0])
+                        .push(kpi_view(
+                            "Revenue MTD",
+                            format!("${:.2}", self.month_to_date.revenue),
+                        ))
+                        .push(kpi_view(
+                            "Net MTD",
+                            format!("${:.2}", self.month_to_date.net),
+                        ))
+                        .push(kpi_view(
+                            "Units Sold MTD",
+                            self.month_to_date.units_sold.to_string(),
+                        ))
+                        .push(kpi_view("Sales MTD", self.month_to_date.sales.to_string())),
+                )
                 .push(
                     Column::new()
                         .spacing(8)
                         .push_maybe(alert_view(
                             self.sales.len(),
                             format!("{} Sales Need to be Completed", self.sales.len()),
+                            HomeList::Sales,
                         ))
                         .push_maybe(alert_view(
                             self.products.len(),
@@ -142,6 +255,7 @@ impl HomeState {
                                 "{} Products are Low and Need to be Made",
                                 self.products.len()
                             ),
+                            HomeList::Products,
                         ))
                         .push_maybe(alert_view(
                             self.parts.len(),
@@ -149,7 +263,9 @@ impl HomeState {
                                 "{} Parts are Low and Need to be Purchased",
                                 self.parts.len()
                             ),
-                        )),
+                            HomeList::Parts,
+                        ))
+                        .push_maybe(self.open_list_view()),
                 )
                 .into(),
         )
@@ -0,0 +1,202 @@
+use iced::{
+    widget::{Button, Column, Container, PickList, Row, Scrollable, Text, TextInput},
+    Alignment, Element, Length,
+};
+
+use crate::{
+    components::{
+        bold_text, error_text, layout, table_column, table_header, table_row_style, table_style,
+        CustomMainButtonStyle,
+    },
+    repo::import::{ImportKind, ImportRow, ImportSource},
+    AppMessage,
+};
+
+const NOT_MAPPED: &str = "(not mapped)";
+
+#[derive(Default, Clone)]
+pub struct ImportState {
+    pub kind: ImportKind,
+    pub path: String,
+    pub source: ImportSource,
+    pub mapping: Vec<Option<usize>>,
+    pub rows: Vec<ImportRow>,
+}
+
+#[derive(Clone, Debug)]
+pub enum ImportMessage {
+    Kind(ImportKind),
+    PathInput(String),
+    Load,
+    Map(usize, Option<usize>),
+    Commit,
+}
+
+impl ImportState {
+    pub fn update(&mut self, message: ImportMessage) {
+        match message {
+            ImportMessage::Kind(k) => {
+                self.kind = k;
+                self.mapping = self.source.auto_map(k);
+                self.rows = Vec::new();
+            }
+            ImportMessage::PathInput(s) => {
+                self.path = s;
+            }
+            ImportMessage::Map(field, column) => {
+                if let Some(m) = self.mapping.get_mut(field) {
+                    *m = column;
+                }
+            }
+            ImportMessage::Load | ImportMessage::Commit => {}
+        }
+    }
+
+    // Only rows that passed every check can be committed, and then all at
+    // once.
+    fn errors(&self) -> usize {
+        self.rows.iter().filter(|r| !r.errors.is_empty()).count()
+    }
+
+    fn mapping_view(&self) -> Column<'_, AppMessage> {
+        let mut options = vec![NOT_MAPPED.to_string()];
+        options.extend(self.source.headers.iter().cloned());
+
+        Column::new()
+            .spacing(8)
+            .extend(self.kind.fields().iter().enumerate().map(|(i, field)| {
+                let selected = self
+                    .mapping
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .and_then(|c| self.source.headers.get(c).cloned())
+                    .unwrap_or(NOT_MAPPED.to_string());
+                let headers = self.source.headers.clone();
+
+                Row::new()
+                    .spacing(12)
+                    .align_items(Alignment::Center)
+                    .push(bold_text(field).width(150))
+                    .push(
+                        PickList::new(options.clone(), Some(selected), move |h: String| {
+                            AppMessage::Import(ImportMessage::Map(
+                                i,
+                                headers.iter().position(|c| *c == h),
+                            ))
+                        })
+                        .width(300),
+                    )
+                    .into()
+            }))
+    }
+
+    fn preview_view(&self) -> Container<'_, AppMessage> {
+        let mut labels = vec!["Line"];
+        labels.extend(self.kind.fields());
+        labels.push("Status");
+
+        Container::new(
+            table_header(&labels).push(Scrollable::new(Column::new().extend(
+                self.rows.iter().map(|row| {
+                    Container::new(
+                        Row::new()
+                            .padding(10)
+                            .push(table_column(&row.line.to_string()))
+                            .extend(row.values.iter().map(|v| table_column(v).into()))
+                            .push(if row.errors.is_empty() {
+                                Column::new().width(300).push(Text::new("OK"))
+                            } else {
+                                Column::new()
+                                    .width(300)
+                                    .extend(row.errors.iter().map(|e| error_text(e).into()))
+                            }),
+                    )
+                    .style(table_row_style())
+                    .into()
+                }),
+            ))),
+        )
+        .style(table_style())
+    }
+
+    pub fn view(&self) -> Element<'_, AppMessage> {
+        let errors = self.errors();
+        let mut commit = Button::new("Import").style(CustomMainButtonStyle);
+        if !self.rows.is_empty() && errors == 0 {
+            commit = commit.on_press(AppMessage::Import(ImportMessage::Commit));
+        }
+
+        layout(
+            Column::new()
+                .width(Length::Fill)
+                .padding([12, 0, 0, 12])
+                .spacing(12)
+                .align_items(Alignment::Center)
+                .push(Text::new("Import".to_string()).size(24))
+                .push(
+                    Row::new()
+                        .spacing(8)
+                        .align_items(Alignment::Center)
+                        .push(bold_text("Import"))
+                        .extend(ImportKind::ALL.iter().map(|k| {
+                            let button = Button::new(Text::new(k.to_string()))
+                                .on_press(AppMessage::Import(ImportMessage::Kind(*k)));
+                            if *k == self.kind {
+                                button.style(CustomMainButtonStyle).into()
+                            } else {
+                                button.into()
+                            }
+                        })),
+                )
+                .push(
+                    Row::new()
+                        .spacing(8)
+                        .align_items(Alignment::Center)
+                        .push(
+                            TextInput::new("Import from (path.csv)", &self.path)
+                                .on_input(|input| {
+                                    AppMessage::Import(ImportMessage::PathInput(input))
+                                })
+                                .on_submit(AppMessage::Import(ImportMessage::Load))
+                                .width(300),
+                        )
+                        .push(
+                            Button::new("Load CSV")
+                                .on_press(AppMessage::Import(ImportMessage::Load))
+                                .style(CustomMainButtonStyle),
+                        ),
+                )
+                .push_maybe(if self.kind == ImportKind::Products {
+                    Some(Text::new(
+                        "One line per part, parts are matched by name and must already exist.",
+                    ))
+                } else {
+                    None
+                })
+                .push_maybe(if self.source.headers.is_empty() {
+                    None
+                } else {
+                    Some(self.mapping_view())
+                })
+                .push_maybe(if self.rows.is_empty() {
+                    None
+                } else {
+                    Some(
+                        Row::new()
+                            .spacing(12)
+                            .align_items(Alignment::Center)
+                            .push(Text::new(format!(
+                                "{} rows, {} with errors",
+                                self.rows.len(),
+                                errors
+                            )))
+                            .push(commit),
+                    )
+                })
+                .push(self.preview_view())
+                .into(),
+        )
+        .into()
+    }
+}
@@ -1,39 +1,75 @@
 #![windows_subsystem = "windows"]
-use std::env;
+use std::{env, time::Duration};
 
 use error::Errorr;
 use home::{get_home, HomeMessage, HomeState, SPS};
 use iced::{executor, window, Application, Command, Element, Theme};
+use sqlx::SqlitePool;
+use tokio::time::sleep;
 
-use clients::{
-    add_client, delete_client, edit_client, get_clients, Client, ClientMessage, ClientState,
-};
-use manufacture::{
-    delete_manufacture, get_manufactures, Manufacture, ManufactureMessage, ManufactureState, ProductToSelect
-};
-use parts::{get_parts, Part, PartToAdd, PartsMessage, PartsState};
-use product::{
-    add_product, delete_product, edit_product, get_product_parts, get_products, Product, ProductMessage, ProductPart, ProductState, ProductToAdd
-};
-use purchase::{
-    delete_purchase, get_purchase_parts, get_purchases, PartToSelect, Purchase, PurchaseMessage, PurchasePart, PurchaseState, PurchaseToAdd
-};
-use rep::{add_rep, delete_rep, edit_rep, get_reps, Rep, RepMessage, RepState};
-use sales::{
-    add_client_set, add_rep_set, get_products_and_clients, get_sale_products_and_client, get_sales,
-    Sale, SaleMessage, SaleProductToAdd, SalesState, PCR, R, SC,
+use clients::{ClientMessage, ClientState};
+use components::{migration_error_view, with_notifications, Notification, NotificationKind};
+use import::{ImportMessage, ImportState};
+use manufacture::{ManufactureMessage, ManufactureState};
+use parts::{PartsMessage, PartsState};
+use product::{ProductMessage, ProductState};
+use purchase::{PurchaseMessage, PurchaseState};
+use receivables::{ReceivablesMessage, ReceivablesState};
+use rep::{RepMessage, RepState};
+use repo::{
+    client::{add_client, delete_client, edit_client, get_clients, Client},
+    export::{
+        export_clients, export_manufactures, export_parts, export_products, export_purchases,
+        export_reps, export_sales,
+    },
+    import::{commit_import, preview_import, read_csv, ImportRow, ImportSource},
+    invoice::write_document,
+    manufacture::{
+        add_manufacture, delete_manufacture, edit_manufacture, get_manufacture_products,
+        get_manufactures, get_products_and_parts, Manufacture, ProductToSelect, ProductsParts,
+    },
+    money,
+    part::{add_part, delete_part, edit_part, get_parts, Part, PartToAdd, PartToSelect},
+    payment::{add_payment, get_receivables, get_sale_payments, Payment, PaymentToAdd, Receivable},
+    product::{
+        add_product, delete_product, edit_product, get_product_parts, get_products, Product,
+        ProductPart, ProductToAdd,
+    },
+    purchase::{
+        add_purchase, delete_purchase, edit_purchase, get_purchase_parts, get_purchases, Purchase,
+        PurchasePart, PurchaseToAdd,
+    },
+    rep::{
+        add_rep, add_rep_payout, delete_rep, edit_rep, get_rep_statement, get_reps, Rep,
+        RepStatement, StatementRange,
+    },
+    report::{get_report, ReportRow},
+    returns::{add_return, ReturnToAdd},
+    sale::{
+        add_sale, change_sale_status, delete_sale, edit_sale, get_products_and_clients,
+        get_sale_products_and_client, get_sales, Sale, SaleProductToAdd, PCR, SC,
+    },
+    setting::{add_setting, get_settings, Setting},
 };
+use reports::{ReportsMessage, ReportsState};
+use sales::{SaleMessage, SalesState};
+use settings::{SettingsMessage, SettingsState};
 
 mod clients;
 mod components;
 mod error;
 mod home;
+mod import;
 mod manufacture;
 mod parts;
 mod product;
 mod purchase;
+mod receivables;
 mod rep;
+mod repo;
+mod reports;
 mod sales;
+mod settings;
 
 #[derive(Debug, Clone)]
 pub enum AppMessage {
@@ -42,23 +78,24 @@ pub enum AppMessage {
     EditProduct(Product),
     ViewProduct(Product),
     SaveProductParts(Result<Vec<ProductPart>, Errorr>),
-    RefetchProducts(Result<(), Errorr>),
+    SaveEditProductParts(Result<Vec<ProductPart>, Errorr>),
+    RefetchProducts(Result<String, Errorr>),
     Sale(SaleMessage),
     GoToSales,
     EditSale(Sale),
     ViewSale(Sale),
-    RefetchSales(Result<(), Errorr>),
-    RefetchSalesAndSale(Result<(), Errorr>),
+    RefetchSales(Result<String, Errorr>),
+    RefetchSalesAndSale(Result<String, Errorr>),
     SaveProductsAndClients(Result<PCR, Errorr>),
     Parts(PartsMessage),
     GoToParts,
     EditPart(Part),
     SaveParts(Result<Vec<Part>, Errorr>),
-    RefetchParts(Result<(), Errorr>),
+    RefetchParts(Result<String, Errorr>),
     Client(ClientMessage),
     GoToClients,
     EditClient(Client),
-    RefetchClients(Result<(), Errorr>),
+    RefetchClients(Result<String, Errorr>),
     SaveClients(Result<Vec<Client>, Errorr>),
     Purchase(PurchaseMessage),
     GoToPurchases,
@@ -67,34 +104,61 @@ pub enum AppMessage {
     EditPurchase(Purchase),
     SavePurchases(Result<Vec<Purchase>, Errorr>),
     SavePurchaseParts(Result<Vec<Part>, Errorr>),
-    RefetchPurchases(Result<(), Errorr>),
+    RefetchPurchases(Result<String, Errorr>),
     Manufacture(ManufactureMessage),
     GoToManufactures,
     EditManufacture(Manufacture),
     SaveManufactures(Result<Vec<Manufacture>, Errorr>),
-    RefetchManufactures(Result<(), Errorr>),
-    SaveManufactureProducts(Result<Vec<Product>, Errorr>),
+    RefetchManufactures(Result<String, Errorr>),
+    SaveManufactureProducts(Result<ProductsParts, Errorr>),
+    SaveManufactureLines(Result<Vec<ProductToSelect>, Errorr>),
     Rep(RepMessage),
     GoToReps,
     EditRep(Rep),
     SaveReps(Result<Vec<Rep>, Errorr>),
-    SetRep(Result<R, Errorr>),
-    RefetchReps(Result<(), Errorr>),
+    SetRep(Result<i64, Errorr>),
+    RefetchReps(Result<String, Errorr>),
+    SaveRepStatement(Result<RepStatement, Errorr>),
+    RefetchRepStatement(Result<String, Errorr>),
+    Receivables(ReceivablesMessage),
+    GoToReceivables,
+    SaveReceivables(Result<Vec<Receivable>, Errorr>),
+    Reports(ReportsMessage),
+    GoToReports,
+    SaveReport(Result<Vec<ReportRow>, Errorr>),
+    Import(ImportMessage),
+    GoToImport,
+    SaveImportSource(Result<ImportSource, Errorr>),
+    SaveImportPreview(Result<Vec<ImportRow>, Errorr>),
+    Imported(Result<String, Errorr>),
+    Settings(SettingsMessage),
+    GoToSettings,
+    SaveSettings(Result<Vec<Setting>, Errorr>),
+    RefetchSettings(Result<String, Errorr>),
     SaveSaleProducts(Result<SC, Errorr>),
+    SaveSalePayments(Result<Vec<Payment>, Errorr>),
     SavePartsProducts(Result<Vec<Part>, Errorr>),
     DoIt(Result<(), Errorr>),
     SaveSales(Result<Vec<Sale>, Errorr>),
     SaveSalesAndSale(Result<Vec<Sale>, Errorr>),
     SaveProducts(Result<Vec<Product>, Errorr>),
     SetClientId(Result<i64, Errorr>),
-    RefetchPurchaseParts(Result<(), Errorr>),
+    RefetchPurchaseParts(Result<String, Errorr>),
     GoToHome,
     Home(HomeMessage),
     SaveHome(Result<SPS, Errorr>),
+    SaveSalesAndOpen(Result<Vec<Sale>, Errorr>, i64),
+    Migrated(Result<(), Errorr>),
+    DismissNotification(usize),
+    Exported(Result<String, Errorr>),
 }
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct App {
+    pool: SqlitePool,
+    migration_error: Option<String>,
+    notifications: Vec<Notification>,
+    notification_id: usize,
     show_products: bool,
     show_sales: bool,
     show_clients: bool,
@@ -102,12 +166,20 @@ pub struct App {
     show_purchases: bool,
     show_manufactures: bool,
     show_reps: bool,
+    show_receivables: bool,
+    show_reports: bool,
+    show_import: bool,
+    show_settings: bool,
     pub sales: SalesState,
     pub products: ProductState,
     pub parts: PartsState,
     pub purchase: PurchaseState,
     pub clients: ClientState,
     pub reps: RepState,
+    pub receivables: ReceivablesState,
+    pub reports: ReportsState,
+    pub import: ImportState,
+    pub settings: SettingsState,
     pub manufacture: ManufactureState,
     pub home: HomeState,
 }
@@ -121,19 +193,93 @@ impl App {
         self.show_purchases = false;
         self.show_manufactures = false;
         self.show_reps = false;
+        self.show_receivables = false;
+        self.show_reports = false;
+        self.show_import = false;
+        self.show_settings = false;
+    }
+
+    // Re-validates the loaded file against the current mapping and database.
+    fn preview_import(&self) -> Command<AppMessage> {
+        if self.import.source.headers.is_empty() {
+            return Command::none();
+        }
+
+        Command::perform(
+            preview_import(
+                self.pool.clone(),
+                self.import.kind,
+                self.import.source.clone(),
+                self.import.mapping.clone(),
+            ),
+            AppMessage::SaveImportPreview,
+        )
+    }
+
+    fn push_notification(&mut self, kind: NotificationKind, message: String) -> usize {
+        self.notification_id += 1;
+        self.notifications.push(Notification {
+            id: self.notification_id,
+            kind,
+            message,
+        });
+        self.notification_id
+    }
+
+    fn notify_error(&mut self, error: Errorr) {
+        self.push_notification(NotificationKind::Error, error.to_string());
+    }
+
+    // Confirmations clear themselves, errors stay until dismissed.
+    fn notify_success(&mut self, message: String) -> Command<AppMessage> {
+        let id = self.push_notification(NotificationKind::Success, message);
+        Command::perform(sleep(Duration::from_secs(4)), move |_| {
+            AppMessage::DismissNotification(id)
+        })
     }
 }
 
 impl Application for App {
     type Executor = executor::Default;
-    type Flags = ();
+    type Flags = SqlitePool;
     type Message = AppMessage;
     type Theme = Theme;
 
-    fn new(_flags: ()) -> (App, Command<Self::Message>) {
+    fn new(pool: SqlitePool) -> (App, Command<Self::Message>) {
+        let app = App {
+            pool,
+            migration_error: None,
+            notifications: Vec::new(),
+            notification_id: 0,
+            show_products: false,
+            show_sales: false,
+            show_clients: false,
+            show_parts: false,
+            show_purchases: false,
+            show_manufactures: false,
+            show_reps: false,
+            show_receivables: false,
+            show_reports: false,
+            show_import: false,
+            show_settings: false,
+            sales: SalesState::default(),
+            products: ProductState::default(),
+            parts: PartsState::default(),
+            purchase: PurchaseState::default(),
+            clients: ClientState::default(),
+            reps: RepState::default(),
+            receivables: ReceivablesState::default(),
+            reports: ReportsState::default(),
+            import: ImportState::default(),
+            settings: SettingsState::default(),
+            manufacture: ManufactureState::default(),
+            home: HomeState::default(),
+        };
+        let pool = app.pool.clone();
+
         (
-            App::default(),
-            Command::perform(get_home(), AppMessage::SaveHome),
+            app,
+            Command::perform(repo::migrate(pool), AppMessage::Migrated),
         )
     }
 
@@ -146,17 +292,83 @@ impl Application for App {
             AppMessage::Home(msg) => {
                 let _ = self.home.update(msg.clone());
 
+                match msg {
+                    HomeMessage::OpenSale(id) => {
+                        self.clear_state();
+                        self.show_sales = true;
+                        Command::perform(get_sales(self.pool.clone()), move |r| {
+                            AppMessage::SaveSalesAndOpen(r, id)
+                        })
+                    }
+                    HomeMessage::OpenProduct(p) => {
+                        self.clear_state();
+                        self.show_products = true;
+                        Command::batch([
+                            Command::perform(
+                                get_products(self.pool.clone()),
+                                AppMessage::SaveProducts,
+                            ),
+                            self.update(AppMessage::ViewProduct(p)),
+                        ])
+                    }
+                    HomeMessage::OpenPart(p) => {
+                        self.clear_state();
+                        self.show_parts = true;
+                        Command::batch([
+                            Command::perform(get_parts(self.pool.clone()), AppMessage::SaveParts),
+                            self.update(AppMessage::EditPart(p)),
+                        ])
+                    }
+                    _ => Command::none(),
+                }
+            }
+            AppMessage::SaveSalesAndOpen(r, id) => match r {
+                Ok(s) => {
+                    self.sales.sales = s;
+                    match self.sales.sales.iter().find(|sale| sale.sale_id == id) {
+                        Some(sale) => {
+                            let sale = sale.clone();
+                            self.update(AppMessage::ViewSale(sale))
+                        }
+                        None => {
+                            self.notify_error(Errorr::NotFound(format!("Sale #{} not found", id)));
+                            Command::none()
+                        }
+                    }
+                }
+                Err(e) => {
+                    self.notify_error(e);
+                    Command::none()
+                }
+            },
+            AppMessage::Exported(r) => match r {
+                Ok(message) => self.notify_success(message),
+                Err(e) => {
+                    self.notify_error(e);
+                    Command::none()
+                }
+            },
+            AppMessage::DismissNotification(id) => {
+                self.notifications.retain(|n| n.id != id);
                 Command::none()
             }
+            AppMessage::Migrated(r) => match r {
+                Ok(_) => Command::perform(get_home(self.pool.clone()), AppMessage::SaveHome),
+                Err(e) => {
+                    self.migration_error = Some(e.to_string());
+                    Command::none()
+                }
+            },
             AppMessage::SaveHome(r) => {
                 match r {
                     Ok(x) => {
                         self.home.sales = x.sales;
                         self.home.products = x.products;
                         self.home.parts = x.parts;
+                        self.home.month_to_date = x.month_to_date;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
@@ -165,29 +377,47 @@ impl Application for App {
                 let _ = self.products.update(msg.clone());
 
                 match msg {
+                    ProductMessage::Export => Command::perform(
+                        export_products(
+                            self.pool.clone(),
+                            self.products.products.clone(),
+                            self.products.export_path.clone(),
+                        ),
+                        |r| {
+                            AppMessage::Exported(
+                                r.map(|(n, path)| format!("Exported {} rows to {}", n, path)),
+                            )
+                        },
+                    ),
                     ProductMessage::Submit(is_edit) => match is_edit {
                         true => {
                             let i = self.products.product_to_edit.clone();
-                            Command::perform(
-                                edit_product(i),
-                                AppMessage::RefetchProducts,
-                            )
+                            let parts = self.products.parts_to_add.clone();
+                            let message = format!("{} updated", i.name);
+                            Command::perform(edit_product(self.pool.clone(), i, parts), |r| {
+                                AppMessage::RefetchProducts(r.map(|_| message))
+                            })
                         }
                         false => {
                             let product_to_add = self.products.product_to_add.clone();
                             let parts_to_add = self.products.filtered_parts.clone();
+                            let message = format!("{} created", product_to_add.name);
                             Command::perform(
-                                add_product(product_to_add, parts_to_add),
-                                AppMessage::RefetchProducts,
+                                add_product(self.pool.clone(), product_to_add, parts_to_add),
+                                |r| AppMessage::RefetchProducts(r.map(|_| message)),
                             )
                         }
                     },
-                    ProductMessage::ShowAddProduct => {
-                        Command::perform(get_parts(), AppMessage::SavePartsProducts)
-                    }
+                    ProductMessage::ShowAddProduct => Command::perform(
+                        get_parts(self.pool.clone()),
+                        AppMessage::SavePartsProducts,
+                    ),
                     ProductMessage::Delete => {
                         let i = self.products.product_to_view.clone();
-                        Command::perform(delete_product(i), AppMessage::RefetchProducts)
+                        let message = format!("{} deleted", i.name);
+                        Command::perform(delete_product(self.pool.clone(), i), |r| {
+                            AppMessage::RefetchProducts(r.map(|_| message))
+                        })
                     }
                     _ => Command::none(),
                 }
@@ -196,74 +426,173 @@ impl Application for App {
                 let _ = self.sales.update(msg.clone());
 
                 match msg {
+                    SaleMessage::Export => Command::perform(
+                        export_sales(
+                            self.pool.clone(),
+                            self.sales.sales.clone(),
+                            self.sales.export_path.clone(),
+                        ),
+                        |r| {
+                            AppMessage::Exported(
+                                r.map(|(n, path)| format!("Exported {} rows to {}", n, path)),
+                            )
+                        },
+                    ),
+                    SaleMessage::WriteDocument(document) => Command::perform(
+                        write_document(
+                            self.pool.clone(),
+                            self.sales.sale_to_view.clone(),
+                            document,
+                            self.sales.document_dir.clone(),
+                        ),
+                        move |r| {
+                            AppMessage::Exported(r.map(|p| format!("{} saved to {}", document, p)))
+                        },
+                    ),
                     SaleMessage::Submit(is_edit) => {
                         if is_edit {
                             let i = self.sales.sale_to_edit.clone();
-                            Command::perform(SalesState::edit_sale(i), AppMessage::DoIt)
+                            let k = self.sales.products_to_add.clone();
+                            let allow_backorder = self.sales.override_shortage;
+                            Command::perform(
+                                edit_sale(self.pool.clone(), i, k, allow_backorder),
+                                AppMessage::DoIt,
+                            )
                         } else {
                             let i = self.sales.add_sales.clone();
-                            let j = self.sales.products_to_select.clone();
                             let k = self.sales.products_to_add.clone();
+                            let allow_backorder = self.sales.override_shortage;
                             Command::perform(
-                                SalesState::add_sales(j, k, i),
-                                AppMessage::RefetchSales,
+                                add_sale(self.pool.clone(), k, i, allow_backorder),
+                                |r| {
+                                    AppMessage::RefetchSales(
+                                        r.map(|id| format!("Sale #{} created", id)),
+                                    )
+                                },
                             )
                         }
                     }
                     SaleMessage::Delete => {
                         let i = self.sales.sale_to_view.clone();
-                        Command::perform(SalesState::delete_sale(i), AppMessage::RefetchSales)
+                        let message = format!("Sale #{} deleted", i.sale_id);
+                        Command::perform(delete_sale(self.pool.clone(), i), |r| {
+                            AppMessage::RefetchSales(r.map(|_| message))
+                        })
                     }
                     SaleMessage::ShowAddProducts => Command::perform(
-                        get_products_and_clients(),
+                        get_products_and_clients(self.pool.clone()),
                         AppMessage::SaveProductsAndClients,
                     ),
                     SaleMessage::CreateClientSubmit => {
                         let c = self.sales.client_to_create.clone();
-                        Command::perform(add_client_set(c), AppMessage::SetClientId)
+                        Command::perform(add_client(self.pool.clone(), c), AppMessage::SetClientId)
                     }
                     SaleMessage::CreateRepSubmit => {
                         let c = self.sales.rep_to_create.clone();
-                        Command::perform(add_rep_set(c), AppMessage::SetRep)
+                        Command::perform(add_rep(self.pool.clone(), c), AppMessage::SetRep)
+                    }
+                    SaleMessage::RecordPayment => {
+                        let id = self.sales.sale_to_view.sale_id;
+                        let payment = self.sales.payment_to_add.clone();
+                        Command::perform(add_payment(self.pool.clone(), id, payment), move |r| {
+                            AppMessage::RefetchSalesAndSale(r.map(|(amount, paid_in_full)| {
+                                let message =
+                                    format!("{} payment recorded on sale #{}", money(amount), id);
+                                if paid_in_full {
+                                    format!("{}, marked Paid", message)
+                                } else {
+                                    message
+                                }
+                            }))
+                        })
+                    }
+                    SaleMessage::SubmitReturn => {
+                        let id = self.sales.sale_to_view.sale_id;
+                        let ret = self.sales.return_to_add.clone();
+                        let message = format!("Return recorded on sale #{}", id);
+                        Command::perform(add_return(self.pool.clone(), id, ret), move |r| {
+                            AppMessage::RefetchSalesAndSale(r.map(|returned| {
+                                if returned {
+                                    format!("{}, marked Returned", message)
+                                } else {
+                                    message
+                                }
+                            }))
+                        })
+                    }
+                    SaleMessage::SetStatus(status) => {
+                        let id = self.sales.sale_to_view.sale_id;
+                        Command::perform(
+                            change_sale_status(self.pool.clone(), id, status),
+                            move |r| {
+                                AppMessage::RefetchSalesAndSale(
+                                    r.map(|_| format!("Sale #{} marked {}", id, status)),
+                                )
+                            },
+                        )
                     }
-                    SaleMessage::Fulfill => Command::perform(
-                        SalesState::fulfill_sale(self.sales.sale_to_view.sale_id),
-                        AppMessage::RefetchSalesAndSale,
-                    ),
                     _ => Command::none(),
                 }
             }
             AppMessage::Purchase(msg) => {
                 let _ = self.purchase.update(msg.clone());
                 match msg {
-                    PurchaseMessage::ShowAddPurchase => {
-                        Command::perform(get_parts(), AppMessage::SavePurchaseParts)
-                    }
+                    PurchaseMessage::Export => Command::perform(
+                        export_purchases(
+                            self.pool.clone(),
+                            self.purchase.purchases.clone(),
+                            self.purchase.export_path.clone(),
+                        ),
+                        |r| {
+                            AppMessage::Exported(
+                                r.map(|(n, path)| format!("Exported {} rows to {}", n, path)),
+                            )
+                        },
+                    ),
+                    PurchaseMessage::ShowAddPurchase => Command::perform(
+                        get_parts(self.pool.clone()),
+                        AppMessage::SavePurchaseParts,
+                    ),
                     PurchaseMessage::CreatePartSubmit => {
                         let p = &self.purchase.part_to_create.name;
 
                         let pp = PartToAdd {
                             name: p.to_string(),
                         };
+                        let message = format!("{} created", p);
 
-                        Command::perform(PartsState::add_part(pp), AppMessage::RefetchPurchaseParts)
+                        Command::perform(add_part(self.pool.clone(), pp), |r| {
+                            AppMessage::RefetchPurchaseParts(r.map(|_| message))
+                        })
                     }
                     PurchaseMessage::Submit(is_edit) => {
                         if is_edit {
-                            Command::none()
+                            let purchase = self.purchase.purchase_to_edit.clone();
+                            let parts = self.purchase.parts_to_add.clone();
+                            let message = format!("Purchase #{} updated", purchase.id);
+                            Command::perform(
+                                edit_purchase(self.pool.clone(), purchase, parts),
+                                |r| AppMessage::RefetchPurchases(r.map(|_| message)),
+                            )
                         } else {
                             let parts_to_add = self.purchase.parts_to_add.clone();
                             let purchase_to_add = self.purchase.purchase_to_add.clone();
-                            let parts = self.purchase.parts.clone();
                             Command::perform(
-                                PurchaseState::add_purchase(parts_to_add, purchase_to_add, parts),
-                                AppMessage::RefetchPurchases,
+                                add_purchase(self.pool.clone(), parts_to_add, purchase_to_add),
+                                |r| {
+                                    AppMessage::RefetchPurchases(
+                                        r.map(|id| format!("Purchase #{} created", id)),
+                                    )
+                                },
                             )
                         }
                     }
                     PurchaseMessage::Delete => {
                         let p = self.purchase.purchase_to_edit.clone();
-                        Command::perform(delete_purchase(p), AppMessage::RefetchPurchases)
+                        let message = format!("Purchase #{} deleted", p.id);
+                        Command::perform(delete_purchase(self.pool.clone(), p), |r| {
+                            AppMessage::RefetchPurchases(r.map(|_| message))
+                        })
                     }
                     _ => Command::none(),
                 }
@@ -271,18 +600,35 @@ impl Application for App {
             AppMessage::Parts(msg) => {
                 let _ = self.parts.update(msg.clone());
                 match msg {
+                    PartsMessage::Export => Command::perform(
+                        export_parts(self.parts.parts.clone(), self.parts.export_path.clone()),
+                        |r| {
+                            AppMessage::Exported(
+                                r.map(|(n, path)| format!("Exported {} rows to {}", n, path)),
+                            )
+                        },
+                    ),
                     PartsMessage::Submit(is_edit) => {
                         if is_edit {
                             let p = self.parts.part_to_edit.clone();
-                            Command::perform(PartsState::edit_part(p), AppMessage::RefetchParts)
+                            let message = format!("{} updated", p.name);
+                            Command::perform(edit_part(self.pool.clone(), p), |r| {
+                                AppMessage::RefetchParts(r.map(|_| message))
+                            })
                         } else {
                             let p = self.parts.part_to_add.clone();
-                            Command::perform(PartsState::add_part(p), AppMessage::RefetchParts)
+                            let message = format!("{} created", p.name);
+                            Command::perform(add_part(self.pool.clone(), p), |r| {
+                                AppMessage::RefetchParts(r.map(|_| message))
+                            })
                         }
                     }
                     PartsMessage::Delete => {
                         let p = self.parts.part_to_edit.clone();
-                        Command::perform(PartsState::delete_part(p), AppMessage::RefetchParts)
+                        let message = format!("{} deleted", p.name);
+                        Command::perform(delete_part(self.pool.clone(), p), |r| {
+                            AppMessage::RefetchParts(r.map(|_| message))
+                        })
                     }
                     _ => Command::none(),
                 }
@@ -291,34 +637,55 @@ impl Application for App {
                 let _ = self.manufacture.update(msg.clone());
 
                 match msg {
-                    ManufactureMessage::ShowAddManufacture => {
-                        Command::perform(get_products(), AppMessage::SaveManufactureProducts)
-                    }
+                    ManufactureMessage::Export => Command::perform(
+                        export_manufactures(
+                            self.pool.clone(),
+                            self.manufacture.manufactures.clone(),
+                            self.manufacture.export_path.clone(),
+                        ),
+                        |r| {
+                            AppMessage::Exported(
+                                r.map(|(n, path)| format!("Exported {} rows to {}", n, path)),
+                            )
+                        },
+                    ),
+                    ManufactureMessage::ShowAddManufacture => Command::perform(
+                        get_products_and_parts(self.pool.clone()),
+                        AppMessage::SaveManufactureProducts,
+                    ),
                     ManufactureMessage::Submit(is_edit) => match is_edit {
                         true => {
                             let i = self.manufacture.manufacture_to_edit.clone();
+                            let products = self.manufacture.products_to_add.clone();
+                            let message = format!("Manufacture #{} updated", i.id);
                             Command::perform(
-                                ManufactureState::edit_manufacture(i),
-                                AppMessage::RefetchManufactures,
+                                edit_manufacture(self.pool.clone(), i, products),
+                                |r| AppMessage::RefetchManufactures(r.map(|_| message)),
                             )
                         }
                         false => {
                             let products_to_add = self.manufacture.products_to_add.clone();
                             let manufacture_to_add = self.manufacture.manufacture_to_add.clone();
-                            let products = self.manufacture.products.clone();
                             Command::perform(
-                                ManufactureState::add_manufacture(
+                                add_manufacture(
+                                    self.pool.clone(),
                                     products_to_add,
                                     manufacture_to_add,
-                                    products,
                                 ),
-                                AppMessage::RefetchManufactures,
+                                |r| {
+                                    AppMessage::RefetchManufactures(
+                                        r.map(|id| format!("Manufacture #{} created", id)),
+                                    )
+                                },
                             )
                         }
                     },
                     ManufactureMessage::Delete => {
                         let m = self.manufacture.manufacture_to_edit.clone();
-                        Command::perform(delete_manufacture(m), AppMessage::RefetchManufactures)
+                        let message = format!("Manufacture #{} deleted", m.id);
+                        Command::perform(delete_manufacture(self.pool.clone(), m), |r| {
+                            AppMessage::RefetchManufactures(r.map(|_| message))
+                        })
                     }
                     _ => Command::none(),
                 }
@@ -326,18 +693,38 @@ impl Application for App {
             AppMessage::Client(msg) => {
                 let _ = self.clients.update(msg.clone());
                 match msg {
+                    ClientMessage::Export => Command::perform(
+                        export_clients(
+                            self.clients.clients.clone(),
+                            self.clients.export_path.clone(),
+                        ),
+                        |r| {
+                            AppMessage::Exported(
+                                r.map(|(n, path)| format!("Exported {} rows to {}", n, path)),
+                            )
+                        },
+                    ),
                     ClientMessage::Submit(is_edit) => {
                         if is_edit {
                             let p = self.clients.client_to_edit.clone();
-                            Command::perform(edit_client(p), AppMessage::RefetchClients)
+                            let message = format!("{} updated", p.name);
+                            Command::perform(edit_client(self.pool.clone(), p), |r| {
+                                AppMessage::RefetchClients(r.map(|_| message))
+                            })
                         } else {
                             let p = self.clients.client_to_add.clone();
-                            Command::perform(add_client(p), AppMessage::RefetchClients)
+                            let message = format!("{} created", p.name);
+                            Command::perform(add_client(self.pool.clone(), p), |r| {
+                                AppMessage::RefetchClients(r.map(|_| message))
+                            })
                         }
                     }
                     ClientMessage::Delete => {
                         let p = self.clients.client_to_edit.clone();
-                        Command::perform(delete_client(p), AppMessage::RefetchClients)
+                        let message = format!("{} deleted", p.name);
+                        Command::perform(delete_client(self.pool.clone(), p), |r| {
+                            AppMessage::RefetchClients(r.map(|_| message))
+                        })
                     }
                     _ => Command::none(),
                 }
@@ -346,18 +733,59 @@ impl Application for App {
                 let _ = self.reps.update(msg.clone());
 
                 match msg {
+                    RepMessage::Export => Command::perform(
+                        export_reps(self.reps.reps.clone(), self.reps.export_path.clone()),
+                        |r| {
+                            AppMessage::Exported(
+                                r.map(|(n, path)| format!("Exported {} rows to {}", n, path)),
+                            )
+                        },
+                    ),
                     RepMessage::Submit(is_edit) => {
                         if is_edit {
                             let p = self.reps.rep_to_edit.clone();
-                            Command::perform(edit_rep(p), AppMessage::RefetchReps)
+                            let message = format!("{} updated", p.name);
+                            Command::perform(edit_rep(self.pool.clone(), p), |r| {
+                                AppMessage::RefetchReps(r.map(|_| message))
+                            })
                         } else {
                             let p = self.reps.rep_to_add.clone();
-                            Command::perform(add_rep(p), AppMessage::RefetchReps)
+                            let message = format!("{} created", p.name);
+                            Command::perform(add_rep(self.pool.clone(), p), |r| {
+                                AppMessage::RefetchReps(r.map(|_| message))
+                            })
                         }
                     }
                     RepMessage::Delete => {
                         let p = self.reps.rep_to_edit.clone();
-                        Command::perform(delete_rep(p), AppMessage::RefetchReps)
+                        let message = format!("{} deleted", p.name);
+                        Command::perform(delete_rep(self.pool.clone(), p), |r| {
+                            AppMessage::RefetchReps(r.map(|_| message))
+                        })
+                    }
+                    RepMessage::LoadStatement => Command::perform(
+                        get_rep_statement(
+                            self.pool.clone(),
+                            self.reps.rep_to_edit.id,
+                            self.reps.statement_range.clone(),
+                        ),
+                        AppMessage::SaveRepStatement,
+                    ),
+                    RepMessage::RecordPayout => {
+                        let name = self.reps.rep_to_edit.name.clone();
+                        Command::perform(
+                            add_rep_payout(
+                                self.pool.clone(),
+                                self.reps.rep_to_edit.id,
+                                self.reps.statement_range.clone(),
+                                self.reps.payout_date.clone(),
+                            ),
+                            move |r| {
+                                AppMessage::RefetchRepStatement(
+                                    r.map(|amount| format!("Paid {} ${:.2}", name, amount)),
+                                )
+                            },
+                        )
                     }
                     _ => Command::none(),
                 }
@@ -365,20 +793,215 @@ impl Application for App {
             AppMessage::GoToReps => {
                 self.clear_state();
                 self.show_reps = true;
-                Command::perform(get_reps(), AppMessage::SaveReps)
+                Command::perform(get_reps(self.pool.clone()), AppMessage::SaveReps)
             }
             AppMessage::EditRep(rep) => {
+                let id = rep.id;
                 self.reps.rep_to_edit = rep;
                 self.reps.edit_rep = true;
+                self.reps.statement_range = StatementRange::default();
+                Command::perform(
+                    get_rep_statement(self.pool.clone(), id, StatementRange::default()),
+                    AppMessage::SaveRepStatement,
+                )
+            }
+            AppMessage::SaveRepStatement(r) => {
+                match r {
+                    Ok(s) => {
+                        self.reps.statement = s;
+                    }
+                    Err(e) => {
+                        self.notify_error(e);
+                    }
+                }
                 Command::none()
             }
+            AppMessage::RefetchRepStatement(r) => match r {
+                Ok(message) => Command::batch([
+                    self.notify_success(message),
+                    Command::perform(get_reps(self.pool.clone()), AppMessage::SaveReps),
+                    Command::perform(
+                        get_rep_statement(
+                            self.pool.clone(),
+                            self.reps.rep_to_edit.id,
+                            self.reps.statement_range.clone(),
+                        ),
+                        AppMessage::SaveRepStatement,
+                    ),
+                ]),
+                Err(e) => {
+                    self.notify_error(e);
+                    Command::none()
+                }
+            },
             AppMessage::SaveReps(r) => {
                 match r {
                     Ok(i) => {
                         self.reps.reps = i;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
+                    }
+                }
+                Command::none()
+            }
+            AppMessage::Receivables(msg) => {
+                self.receivables.update(msg.clone());
+
+                match msg {
+                    ReceivablesMessage::OpenSale(id) => {
+                        self.clear_state();
+                        self.show_sales = true;
+                        Command::perform(get_sales(self.pool.clone()), move |r| {
+                            AppMessage::SaveSalesAndOpen(r, id)
+                        })
+                    }
+                    _ => Command::none(),
+                }
+            }
+            AppMessage::GoToReceivables => {
+                self.clear_state();
+                self.show_receivables = true;
+                Command::perform(
+                    get_receivables(self.pool.clone()),
+                    AppMessage::SaveReceivables,
+                )
+            }
+            AppMessage::SaveReceivables(r) => {
+                match r {
+                    Ok(r) => {
+                        self.receivables.receivables = r;
+                    }
+                    Err(e) => {
+                        self.notify_error(e);
+                    }
+                }
+                Command::none()
+            }
+            AppMessage::Reports(msg) => {
+                self.reports.update(msg.clone());
+
+                match msg {
+                    ReportsMessage::Run | ReportsMessage::Grouping(_) => Command::perform(
+                        get_report(self.pool.clone(), self.reports.filter.clone()),
+                        AppMessage::SaveReport,
+                    ),
+                    _ => Command::none(),
+                }
+            }
+            AppMessage::GoToReports => {
+                self.clear_state();
+                self.show_reports = true;
+                Command::perform(
+                    get_report(self.pool.clone(), self.reports.filter.clone()),
+                    AppMessage::SaveReport,
+                )
+            }
+            AppMessage::SaveReport(r) => {
+                match r {
+                    Ok(rows) => {
+                        self.reports.rows = rows;
+                    }
+                    Err(e) => {
+                        self.notify_error(e);
+                    }
+                }
+                Command::none()
+            }
+            AppMessage::Import(msg) => {
+                self.import.update(msg.clone());
+
+                match msg {
+                    ImportMessage::Load => Command::perform(
+                        read_csv(self.import.path.clone()),
+                        AppMessage::SaveImportSource,
+                    ),
+                    ImportMessage::Kind(_) | ImportMessage::Map(_, _) => self.preview_import(),
+                    ImportMessage::Commit => {
+                        let kind = self.import.kind;
+                        Command::perform(
+                            commit_import(
+                                self.pool.clone(),
+                                kind,
+                                self.import.source.clone(),
+                                self.import.mapping.clone(),
+                            ),
+                            move |r| {
+                                AppMessage::Imported(r.map(|n| {
+                                    format!("Imported {} {}", n, kind.to_string().to_lowercase())
+                                }))
+                            },
+                        )
+                    }
+                    _ => Command::none(),
+                }
+            }
+            AppMessage::GoToImport => {
+                self.clear_state();
+                self.show_import = true;
+                self.preview_import()
+            }
+            AppMessage::SaveImportSource(r) => match r {
+                Ok(source) => {
+                    self.import.mapping = source.auto_map(self.import.kind);
+                    self.import.source = source;
+                    self.import.rows = Vec::new();
+                    self.preview_import()
+                }
+                Err(e) => {
+                    self.notify_error(e);
+                    Command::none()
+                }
+            },
+            AppMessage::SaveImportPreview(r) => {
+                match r {
+                    Ok(rows) => {
+                        self.import.rows = rows;
+                    }
+                    Err(e) => {
+                        self.notify_error(e);
+                    }
+                }
+                Command::none()
+            }
+            AppMessage::Imported(r) => match r {
+                Ok(message) => {
+                    self.import.source = ImportSource::default();
+                    self.import.mapping = Vec::new();
+                    self.import.rows = Vec::new();
+                    self.notify_success(message)
+                }
+                Err(e) => {
+                    self.notify_error(e);
+                    self.preview_import()
+                }
+            },
+            AppMessage::Settings(msg) => {
+                self.settings.update(msg.clone());
+
+                match msg {
+                    SettingsMessage::Submit => {
+                        let s = self.settings.setting_to_add.clone();
+                        let message = format!("Settings effective {} added", s.effective_date);
+                        Command::perform(add_setting(self.pool.clone(), s), |r| {
+                            AppMessage::RefetchSettings(r.map(|_| message))
+                        })
+                    }
+                    _ => Command::none(),
+                }
+            }
+            AppMessage::GoToSettings => {
+                self.clear_state();
+                self.show_settings = true;
+                Command::perform(get_settings(self.pool.clone()), AppMessage::SaveSettings)
+            }
+            AppMessage::SaveSettings(r) => {
+                match r {
+                    Ok(s) => {
+                        self.settings.settings = s;
+                    }
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
@@ -387,27 +1010,27 @@ impl Application for App {
                 self.purchase.view_purchase = true;
                 self.purchase.purchase_to_view = p.clone();
                 Command::perform(
-                    get_purchase_parts(p.id),
+                    get_purchase_parts(self.pool.clone(), p.id),
                     AppMessage::SavePurchasePartsToView,
                 )
             }
             AppMessage::SavePurchasePartsToView(r) => {
                 match r {
                     Ok(pp) => self.purchase.purchase_parts_to_view = pp,
-                    Err(_) => println!("error"),
+                    Err(e) => self.notify_error(e),
                 }
                 Command::none()
             }
             AppMessage::GoToProducts => {
                 self.clear_state();
                 self.show_products = true;
-                Command::perform(get_products(), AppMessage::SaveProducts)
+                Command::perform(get_products(self.pool.clone()), AppMessage::SaveProducts)
             }
             AppMessage::ViewProduct(p) => {
                 self.products.product_to_view = p.clone();
                 self.products.view_product = true;
                 Command::perform(
-                    get_product_parts(p.product_id),
+                    get_product_parts(self.pool.clone(), p.product_id),
                     AppMessage::SaveProductParts,
                 )
             }
@@ -416,44 +1039,120 @@ impl Application for App {
                     Ok(p) => {
                         self.products.product_parts_to_view = p;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
             }
+            // The bill of materials is fetched again rather than taken from the
+            // product view, which may not have loaded it yet.
             AppMessage::EditProduct(product) => {
+                let id = product.product_id;
                 self.products.product_to_edit = product;
                 self.products.edit_product = true;
-                Command::none()
+                Command::perform(
+                    get_product_parts(self.pool.clone(), id),
+                    AppMessage::SaveEditProductParts,
+                )
             }
+            AppMessage::SaveEditProductParts(r) => match r {
+                Ok(p) => {
+                    self.products.parts_to_add = p
+                        .iter()
+                        .map(|pp| PartToSelect {
+                            part_id: pp.part_id,
+                            name: pp.name.clone(),
+                            cost: pp.cost.to_string(),
+                            qty: pp.qty,
+                            ..Default::default()
+                        })
+                        .collect();
+                    self.products.product_parts_to_view = p;
+                    Command::perform(get_parts(self.pool.clone()), AppMessage::SavePartsProducts)
+                }
+                Err(e) => {
+                    self.notify_error(e);
+                    Command::none()
+                }
+            },
             AppMessage::GoToSales => {
                 self.clear_state();
 
                 self.show_sales = true;
-                Command::perform(get_sales(), AppMessage::SaveSales)
+                Command::perform(get_sales(self.pool.clone()), AppMessage::SaveSales)
             }
             AppMessage::GoToHome => {
                 self.clear_state();
-                Command::perform(get_home(), AppMessage::SaveHome)
+                Command::perform(get_home(self.pool.clone()), AppMessage::SaveHome)
             }
             AppMessage::GoToClients => {
                 self.clear_state();
                 self.show_clients = true;
-                Command::perform(get_clients(), AppMessage::SaveClients)
+                Command::perform(get_clients(self.pool.clone()), AppMessage::SaveClients)
             }
             AppMessage::EditSale(s) => {
                 self.sales.sale_to_edit = s;
+                self.sales.sale_to_edit.discount_input = self
+                    .sales
+                    .sale_to_edit
+                    .discount
+                    .filter(|d| *d != 0.00)
+                    .map(|d| d.to_string())
+                    .unwrap_or_default();
                 self.sales.edit_sale = true;
-                Command::none()
+                self.sales.products_to_add = self
+                    .sales
+                    .sale_products_to_view
+                    .iter()
+                    .map(|p| SaleProductToAdd {
+                        product_id: p.product_id,
+                        name: p.name.clone(),
+                        qty: p.qty,
+                        msrp: p.msrp_at_sale,
+                        cost: p.cost_at_sale,
+                        units: p.units + p.qty,
+                        discount: p.discount,
+                        discount_input: if p.discount != 0.00 {
+                            p.discount.to_string()
+                        } else {
+                            String::new()
+                        },
+                        discount_percent: p.discount_percent,
+                    })
+                    .collect();
+                Command::perform(
+                    get_products_and_clients(self.pool.clone()),
+                    AppMessage::SaveProductsAndClients,
+                )
             }
             AppMessage::ViewSale(s) => {
                 self.sales.sale_to_view = s.clone();
                 self.sales.view_sale = true;
-                Command::perform(
-                    get_sale_products_and_client(s.sale_id, s.client_id),
-                    AppMessage::SaveSaleProducts,
-                )
+                self.sales.payment_to_add = PaymentToAdd::default();
+                self.sales.add_return = false;
+                self.sales.return_to_add = ReturnToAdd::default();
+                Command::batch([
+                    Command::perform(
+                        get_sale_products_and_client(self.pool.clone(), s.sale_id, s.client_id),
+                        AppMessage::SaveSaleProducts,
+                    ),
+                    Command::perform(
+                        get_sale_payments(self.pool.clone(), s.sale_id),
+                        AppMessage::SaveSalePayments,
+                    ),
+                ])
+            }
+            AppMessage::SaveSalePayments(r) => {
+                match r {
+                    Ok(p) => {
+                        self.sales.payments = p;
+                    }
+                    Err(e) => {
+                        self.notify_error(e);
+                    }
+                }
+                Command::none()
             }
             AppMessage::EditClient(c) => {
                 self.clients.client_to_edit = c;
@@ -463,7 +1162,7 @@ impl Application for App {
             AppMessage::GoToParts => {
                 self.clear_state();
                 self.show_parts = true;
-                Command::perform(get_parts(), AppMessage::SaveParts)
+                Command::perform(get_parts(self.pool.clone()), AppMessage::SaveParts)
             }
             AppMessage::EditPart(p) => {
                 self.parts.part_to_edit = p;
@@ -473,65 +1172,96 @@ impl Application for App {
             AppMessage::GoToPurchases => {
                 self.clear_state();
                 self.show_purchases = true;
-                Command::perform(get_purchases(), AppMessage::SavePurchases)
+                Command::perform(get_purchases(self.pool.clone()), AppMessage::SavePurchases)
             }
             AppMessage::EditPurchase(p) => {
                 self.purchase.purchase_to_edit = p;
                 self.purchase.edit_purchase = true;
-                Command::none()
+                self.purchase.parts_to_add = self
+                    .purchase
+                    .purchase_parts_to_view
+                    .iter()
+                    .map(|pp| PartToSelect {
+                        part_id: pp.part_id,
+                        name: pp.name.clone(),
+                        cost: pp.cost.to_string(),
+                        qty: pp.qty,
+                        ..Default::default()
+                    })
+                    .collect();
+                Command::perform(get_parts(self.pool.clone()), AppMessage::SavePurchaseParts)
             }
             AppMessage::GoToManufactures => {
                 self.clear_state();
                 self.show_manufactures = true;
-                Command::perform(get_manufactures(), AppMessage::SaveManufactures)
+                Command::perform(
+                    get_manufactures(self.pool.clone()),
+                    AppMessage::SaveManufactures,
+                )
             }
             AppMessage::EditManufacture(m) => {
+                let id = m.id;
                 self.manufacture.manufacture_to_edit = m;
                 self.manufacture.edit_manufacture = true;
-                Command::none()
+                Command::perform(
+                    get_manufacture_products(self.pool.clone(), id),
+                    AppMessage::SaveManufactureLines,
+                )
             }
-            AppMessage::DoIt(r) => {
-                match r {
-                    Ok(()) => {
-                        self.sales.products_to_add = Vec::new();
-                        self.sales.add_sales = sales::Sale::default();
-                    }
-                    Err(_) => {
-                        println!("error");
-                    }
+            AppMessage::DoIt(r) => match r {
+                Ok(()) => {
+                    self.sales.products_to_add = Vec::new();
+                    self.sales.add_sales = Sale::default();
+                    Command::batch([
+                        self.notify_success(format!(
+                            "Sale #{} updated",
+                            self.sales.sale_to_edit.sale_id
+                        )),
+                        Command::perform(get_sales(self.pool.clone()), AppMessage::SaveSales),
+                    ])
                 }
-                Command::none()
-            }
+                Err(e) => {
+                    self.notify_error(e);
+                    Command::none()
+                }
+            },
             AppMessage::SaveSales(r) => {
                 match r {
                     Ok(s) => {
                         self.sales.sales = s;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
             }
-            AppMessage::SaveSalesAndSale(r) => {
-                match r {
-                    Ok(s) => {
-                        self.sales.sales = s;
-                        self.sales.sale_to_view.status = "COMPLETED".to_string();
-                    }
-                    Err(_) => {
-                        println!("error");
+            // Reopens the viewed sale so its status, history and stock figures
+            // are current.
+            AppMessage::SaveSalesAndSale(r) => match r {
+                Ok(s) => {
+                    self.sales.sales = s;
+                    let id = self.sales.sale_to_view.sale_id;
+                    match self.sales.sales.iter().find(|sale| sale.sale_id == id) {
+                        Some(sale) => {
+                            let sale = sale.clone();
+                            self.update(AppMessage::ViewSale(sale))
+                        }
+                        None => Command::none(),
                     }
                 }
-                Command::none()
-            }
+                Err(e) => {
+                    self.notify_error(e);
+                    Command::none()
+                }
+            },
             AppMessage::SaveProducts(r) => {
                 match r {
                     Ok(i) => {
                         self.products.products = i;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
@@ -542,8 +1272,8 @@ impl Application for App {
                         self.clients.clients = clients.clone();
                         self.sales.clients = clients;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
@@ -553,9 +1283,11 @@ impl Application for App {
                     Ok(s) => {
                         self.sales.sale_products_to_view = s.sale_products;
                         self.sales.client_to_view = s.client;
+                        self.sales.status_history = s.history;
+                        self.sales.returns = s.returns;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
@@ -563,27 +1295,38 @@ impl Application for App {
             AppMessage::SetClientId(r) => match r {
                 Ok(i) => {
                     self.sales.add_sales.client_id = i;
-                    Command::perform(
-                        get_products_and_clients(),
-                        AppMessage::SaveProductsAndClients,
-                    )
+                    self.sales.add_sales.client_name = self.sales.client_to_create.name.clone();
+                    Command::batch([
+                        self.notify_success(format!(
+                            "{} created",
+                            self.sales.add_sales.client_name
+                        )),
+                        Command::perform(
+                            get_products_and_clients(self.pool.clone()),
+                            AppMessage::SaveProductsAndClients,
+                        ),
+                    ])
                 }
-                Err(_) => {
-                    println!("error");
+                Err(e) => {
+                    self.notify_error(e);
                     Command::none()
                 }
             },
             AppMessage::SetRep(r) => match r {
                 Ok(i) => {
-                    self.sales.add_sales.rep_id = Some(i.id);
-                    self.sales.add_sales.rep_name = i.name;
-                    Command::perform(
-                        get_products_and_clients(),
-                        AppMessage::SaveProductsAndClients,
-                    )
+                    self.sales.add_sales.rep_id = Some(i);
+                    self.sales.add_sales.rep_name = self.sales.rep_to_create.name.clone();
+                    self.sales.add_sales.rep_percentage = self.sales.rep_to_create.percentage;
+                    Command::batch([
+                        self.notify_success(format!("{} created", self.sales.add_sales.rep_name)),
+                        Command::perform(
+                            get_products_and_clients(self.pool.clone()),
+                            AppMessage::SaveProductsAndClients,
+                        ),
+                    ])
                 }
-                Err(_) => {
-                    println!("error");
+                Err(e) => {
+                    self.notify_error(e);
                     Command::none()
                 }
             },
@@ -592,8 +1335,8 @@ impl Application for App {
                     Ok(p) => {
                         self.parts.parts = p;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
@@ -616,12 +1359,21 @@ impl Application for App {
                             x.push(part_product);
                         }
 
+                        if self.products.edit_product {
+                            for line in &self.products.parts_to_add {
+                                if let Some(ps) = x.iter_mut().find(|ps| ps.part_id == line.part_id)
+                                {
+                                    ps.qty = line.qty;
+                                }
+                            }
+                        }
+
                         self.products.parts = p;
                         self.products.parts_to_select = x.clone();
                         self.products.filtered_parts = x;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
@@ -631,8 +1383,8 @@ impl Application for App {
                     Ok(p) => {
                         self.purchase.purchases = p;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
@@ -655,12 +1407,22 @@ impl Application for App {
                             x.push(part_to_select);
                         }
 
+                        if self.purchase.edit_purchase {
+                            for line in &self.purchase.parts_to_add {
+                                if let Some(ps) = x.iter_mut().find(|ps| ps.part_id == line.part_id)
+                                {
+                                    ps.cost = line.cost.clone();
+                                    ps.qty = line.qty;
+                                }
+                            }
+                        }
+
                         self.purchase.parts = p;
                         self.purchase.parts_to_select = x.clone();
                         self.purchase.filtered_parts = x;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
@@ -670,18 +1432,18 @@ impl Application for App {
                     Ok(m) => {
                         self.manufacture.manufactures = m;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
             }
             AppMessage::SaveManufactureProducts(r) => {
                 match r {
-                    Ok(p) => {
+                    Ok(ppr) => {
                         let mut x = Vec::new();
 
-                        for product in p.iter() {
+                        for product in ppr.products.iter() {
                             let product_to_select = ProductToSelect {
                                 product_id: product.product_id,
                                 name: product.name.clone(),
@@ -691,16 +1453,41 @@ impl Application for App {
                             x.push(product_to_select);
                         }
 
-                        self.manufacture.products = p;
+                        if self.manufacture.edit_manufacture {
+                            for line in &self.manufacture.products_to_add {
+                                if let Some(ps) =
+                                    x.iter_mut().find(|ps| ps.product_id == line.product_id)
+                                {
+                                    ps.qty = line.qty;
+                                }
+                            }
+                        }
+
+                        self.manufacture.products = ppr.products;
+                        self.manufacture.part_requirements = ppr.part_requirements;
                         self.manufacture.products_to_select = x.clone();
                         self.manufacture.filtered_products = x;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
             }
+            AppMessage::SaveManufactureLines(r) => match r {
+                Ok(lines) => {
+                    self.manufacture.products_to_add = lines.clone();
+                    self.manufacture.original_products = lines;
+                    Command::perform(
+                        get_products_and_parts(self.pool.clone()),
+                        AppMessage::SaveManufactureProducts,
+                    )
+                }
+                Err(e) => {
+                    self.notify_error(e);
+                    Command::none()
+                }
+            },
             AppMessage::SaveProductsAndClients(r) => {
                 match r {
                     Ok(pc) => {
@@ -719,88 +1506,150 @@ impl Application for App {
                                 msrp: p.msrp,
                                 units: p.units,
                                 qty: 0,
+                                ..Default::default()
                             };
 
                             x.push(ps);
                         }
 
+                        // Lines already on the sale being edited keep their
+                        // at-sale prices, and their units are still on it.
+                        if self.sales.edit_sale {
+                            for line in &self.sales.products_to_add {
+                                if let Some(ps) =
+                                    x.iter_mut().find(|ps| ps.product_id == line.product_id)
+                                {
+                                    ps.qty = line.qty;
+                                    ps.cost = line.cost;
+                                    ps.msrp = line.msrp;
+                                    ps.units = line.units;
+                                }
+                            }
+                        }
+
                         self.sales.products_to_select = x.clone();
                         self.sales.filtered_products = x;
                     }
-                    Err(_) => {
-                        println!("error");
+                    Err(e) => {
+                        self.notify_error(e);
                     }
                 }
                 Command::none()
             }
             AppMessage::RefetchPurchaseParts(r) => match r {
-                Ok(_) => Command::perform(get_parts(), AppMessage::SavePurchaseParts),
-                Err(_) => {
-                    println!("error");
+                Ok(message) => Command::batch([
+                    self.notify_success(message),
+                    Command::perform(get_parts(self.pool.clone()), AppMessage::SavePurchaseParts),
+                ]),
+                Err(e) => {
+                    self.notify_error(e);
                     Command::none()
                 }
             },
             AppMessage::RefetchPurchases(r) => match r {
-                Ok(_) => {
+                Ok(message) => {
                     self.purchase.purchase_to_add = PurchaseToAdd::default();
                     self.purchase.parts_to_add = Vec::new();
-                    Command::perform(get_purchases(), AppMessage::SavePurchases)
+                    Command::batch([
+                        self.notify_success(message),
+                        Command::perform(
+                            get_purchases(self.pool.clone()),
+                            AppMessage::SavePurchases,
+                        ),
+                    ])
                 }
-                Err(_) => {
-                    println!("error");
+                Err(e) => {
+                    self.notify_error(e);
                     Command::none()
                 }
             },
             AppMessage::RefetchSales(r) => match r {
-                Ok(_) => Command::perform(get_sales(), AppMessage::SaveSales),
-                Err(_) => {
-                    println!("error");
+                Ok(message) => {
+                    self.sales.add_sales = Sale::default();
+                    Command::batch([
+                        self.notify_success(message),
+                        Command::perform(get_sales(self.pool.clone()), AppMessage::SaveSales),
+                    ])
+                }
+                Err(e) => {
+                    self.notify_error(e);
                     Command::none()
                 }
             },
             AppMessage::RefetchSalesAndSale(r) => match r {
-                Ok(_) => Command::perform(get_sales(), AppMessage::SaveSalesAndSale),
-                Err(_) => {
-                    println!("error");
+                Ok(message) => Command::batch([
+                    self.notify_success(message),
+                    Command::perform(get_sales(self.pool.clone()), AppMessage::SaveSalesAndSale),
+                ]),
+                Err(e) => {
+                    self.notify_error(e);
                     Command::none()
                 }
             },
             AppMessage::RefetchReps(r) => match r {
-                Ok(_) => Command::perform(get_reps(), AppMessage::SaveReps),
-                Err(_) => {
-                    println!("error");
+                Ok(message) => Command::batch([
+                    self.notify_success(message),
+                    Command::perform(get_reps(self.pool.clone()), AppMessage::SaveReps),
+                ]),
+                Err(e) => {
+                    self.notify_error(e);
+                    Command::none()
+                }
+            },
+            AppMessage::RefetchSettings(r) => match r {
+                Ok(message) => Command::batch([
+                    self.notify_success(message),
+                    Command::perform(get_settings(self.pool.clone()), AppMessage::SaveSettings),
+                ]),
+                Err(e) => {
+                    self.notify_error(e);
                     Command::none()
                 }
             },
             AppMessage::RefetchClients(r) => match r {
-                Ok(_) => Command::perform(get_clients(), AppMessage::SaveClients),
-                Err(_) => {
-                    println!("error");
+                Ok(message) => Command::batch([
+                    self.notify_success(message),
+                    Command::perform(get_clients(self.pool.clone()), AppMessage::SaveClients),
+                ]),
+                Err(e) => {
+                    self.notify_error(e);
                     Command::none()
                 }
             },
             AppMessage::RefetchParts(r) => match r {
-                Ok(_) => Command::perform(get_parts(), AppMessage::SaveParts),
-                Err(_) => {
-                    println!("error");
+                Ok(message) => Command::batch([
+                    self.notify_success(message),
+                    Command::perform(get_parts(self.pool.clone()), AppMessage::SaveParts),
+                ]),
+                Err(e) => {
+                    self.notify_error(e);
                     Command::none()
                 }
             },
             AppMessage::RefetchManufactures(r) => match r {
-                Ok(_) => Command::perform(get_manufactures(), AppMessage::SaveManufactures),
-                Err(_) => {
-                    println!("error");
+                Ok(message) => Command::batch([
+                    self.notify_success(message),
+                    Command::perform(
+                        get_manufactures(self.pool.clone()),
+                        AppMessage::SaveManufactures,
+                    ),
+                ]),
+                Err(e) => {
+                    self.notify_error(e);
                     Command::none()
                 }
             },
             AppMessage::RefetchProducts(r) => match r {
-                Ok(_) => {
+                Ok(message) => {
                     self.products.product_to_add = ProductToAdd::default();
                     self.products.parts_to_add = Vec::new();
-                    Command::perform(get_products(), AppMessage::SaveProducts)
+                    Command::batch([
+                        self.notify_success(message),
+                        Command::perform(get_products(self.pool.clone()), AppMessage::SaveProducts),
+                    ])
                 }
-                Err(_) => {
-                    println!("error");
+                Err(e) => {
+                    self.notify_error(e);
                     Command::none()
                 }
             },
@@ -808,7 +1657,11 @@ impl Application for App {
     }
 
     fn view(&self) -> Element<Self::Message> {
-        if self.show_products {
+        if let Some(message) = &self.migration_error {
+            return migration_error_view(message);
+        }
+
+        let screen = if self.show_products {
             ProductState::view(&self.products)
         } else if self.show_sales {
             SalesState::view(&self.sales)
@@ -822,9 +1675,19 @@ impl Application for App {
             ManufactureState::view(&self.manufacture)
         } else if self.show_reps {
             RepState::view(&self.reps)
+        } else if self.show_receivables {
+            ReceivablesState::view(&self.receivables)
+        } else if self.show_reports {
+            ReportsState::view(&self.reports)
+        } else if self.show_import {
+            ImportState::view(&self.import)
+        } else if self.show_settings {
+            SettingsState::view(&self.settings)
         } else {
             HomeState::view(&self.home)
-        }
+        };
+
+        with_notifications(screen, &self.notifications)
     }
 }
 
@@ -840,12 +1703,12 @@ async fn main() -> iced::Result {
         .unwrap()
         .to_str()
         .unwrap();
-    env::set_var("DATABASE_URL", format!("{}/db.db", contents));
-    App::run(iced::Settings::from(iced::Settings {
+    let pool = repo::connect(&format!("sqlite://{}/db.db", contents)).unwrap();
+    App::run(iced::Settings {
         window: window::Settings {
             icon: Some(window::icon::from_file(format!("{}/assets/icon.ico", contents)).unwrap()),
             ..Default::default()
         },
-        ..Default::default()
-    }))
+        ..iced::Settings::with_flags(pool)
+    })
 }
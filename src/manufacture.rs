@@ -1,44 +1,30 @@
-use std::env;
-
 use iced::{
     alignment::Horizontal,
     widget::{Button, Column, Container, Row, Scrollable, Text, TextInput},
     Alignment, Element, Length,
 };
-use sqlx::SqlitePool;
 
 use crate::{
     components::{
-        add_button, bold_text, close_button, layout, table_column, table_header, table_row_style,
-        table_style, text_input_column, CustomButtonStyle, CustomMainButtonStyle,
+        add_button, bold_text, close_button, export_row, layout, shortage_view, table_column,
+        table_header, table_row_style, table_style, text_input_column, CustomButtonStyle,
+        CustomMainButtonStyle,
+    },
+    repo::{
+        manufacture::{
+            manufacture_shortages, quantity_changes, Manufacture, ManufactureToAdd,
+            PartRequirement, ProductToSelect,
+        },
+        product::Product,
+        Shortage,
     },
-    error::Errorr,
-    product::Product,
     AppMessage,
 };
 
-#[derive(Default, Clone, Debug)]
-pub struct Manufacture {
-    pub id: i64,
-    pub date: String,
-}
-
-#[derive(Default, Clone, Debug)]
-pub struct ManufactureToAdd {
-    pub date: String,
-    pub qty: i64,
-}
-
-#[derive(Default, Clone, Debug)]
-pub struct ProductToSelect {
-    pub product_id: i64,
-    pub name: String,
-    pub qty: i64,
-}
-
 #[derive(Default, Clone)]
 pub struct ManufactureState {
     pub manufactures: Vec<Manufacture>,
+    pub export_path: String,
     pub manufacture_to_add: ManufactureToAdd,
     add_manufacture: bool,
     pub manufacture_to_edit: Manufacture,
@@ -46,8 +32,11 @@ pub struct ManufactureState {
     pub products: Vec<Product>,
     pub products_to_select: Vec<ProductToSelect>,
     pub products_to_add: Vec<ProductToSelect>,
+    pub original_products: Vec<ProductToSelect>,
     query: String,
     pub filtered_products: Vec<ProductToSelect>,
+    pub part_requirements: Vec<PartRequirement>,
+    allow_backorder: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -59,6 +48,10 @@ pub enum ManufactureMessage {
     Submit(bool),
     Delete,
     Query(String),
+    AllowBackorder(bool),
+    BackorderReason(String),
+    ExportPath(String),
+    Export,
 }
 
 pub fn select_header() -> Container<'static, AppMessage> {
@@ -72,138 +65,18 @@ pub fn select_header() -> Container<'static, AppMessage> {
     .style(table_row_style())
 }
 
-pub async fn get_manufactures() -> Result<Vec<Manufacture>, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let manufactures = sqlx::query_as!(Manufacture, "SELECT * FROM Manufacture")
-        .fetch_all(&pool)
-        .await?;
-
-    Ok(manufactures)
-}
-
-pub async fn delete_manufacture(manufacture: Manufacture) -> Result<(), Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let id = manufacture.id;
-
-    sqlx::query!(
-        "
-        DELETE FROM Manufacture
-        WHERE id = ?
-        ",
-        id
-        )
-        .execute(&pool)
-        .await?;
-
-    Ok(())
-}
-
 impl ManufactureState {
-    pub async fn add_manufacture(
-        products_to_add: Vec<ProductToSelect>,
-        manufacture_to_add: ManufactureToAdd,
-        products: Vec<Product>,
-    ) -> Result<(), Errorr> {
-        let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-        let date = manufacture_to_add.date;
-
-        let r = sqlx::query!(
-            "
-            INSERT INTO Manufacture (date)
-            VALUES (?)
-            ",
-            date,
-        )
-        .execute(&pool)
-        .await?;
-
-        let manufacture_id = r.last_insert_rowid();
-
-        for product in &products_to_add {
-            sqlx::query!(
-                "
-                INSERT INTO ManufactureProduct (qty, manufacture_id, product_id)
-                VALUES (?,?,?)
-                ",
-                product.qty,
-                manufacture_id,
-                product.product_id
-            )
-            .execute(&pool)
-            .await?;
-
-            let productt = products
-                .iter()
-                .find(|p| p.product_id == product.product_id)
-                .unwrap();
-            let units = productt.units + product.qty;
-
-            sqlx::query!(
-                "
-                UPDATE Product
-                SET units = ? 
-                WHERE product_id = ?
-                ",
-                units,
-                product.product_id
+    // Part shortages for the current form. An edit only needs stock for the
+    // quantities added on top of what the run already built.
+    fn shortages(&self) -> Vec<Shortage> {
+        if self.edit_manufacture {
+            manufacture_shortages(
+                &quantity_changes(&self.original_products, &self.products_to_add),
+                &self.part_requirements,
             )
-            .execute(&pool)
-            .await?;
-
-            let product_parts = sqlx::query!(
-                "
-                SELECT ProductPart.part_id, ProductPart.qty, Part.units_left, ProductPart.product_id 
-                FROM ProductPart 
-                JOIN Part ON Part.part_id = ProductPart.part_id
-                WHERE product_id = ?
-                ",
-                product.product_id
-                )
-                .fetch_all(&pool)
-                .await?;
-
-            for product_part in &product_parts {
-                let units_left = product_part.units_left - product_part.qty;
-
-                sqlx::query!(
-                    "
-                    UPDATE Part
-                    SET  units_left = ? 
-                    WHERE part_id = ?
-                    ",
-                    units_left,
-                    product_part.part_id
-                )
-                .execute(&pool)
-                .await?;
-            }
+        } else {
+            manufacture_shortages(&self.products_to_add, &self.part_requirements)
         }
-
-        Ok(())
-    }
-
-    pub async fn edit_manufacture(manufacture: Manufacture) -> Result<(), Errorr> {
-        let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-        let id = manufacture.id;
-        let date = manufacture.date;
-
-        sqlx::query!(
-            "
-            UPDATE Manufacture
-            SET date = ?
-            WHERE id = ?
-            ",
-            date,
-            id
-        )
-        .execute(&pool)
-        .await?;
-
-        Ok(())
     }
 
     fn select_product(&self) -> Container<'_, AppMessage> {
@@ -299,6 +172,10 @@ impl ManufactureState {
 
     pub fn update(&mut self, message: ManufactureMessage) {
         match message {
+            ManufactureMessage::ExportPath(p) => {
+                self.export_path = p;
+            }
+            ManufactureMessage::Export => {}
             ManufactureMessage::DateInput(d, is_edit) => {
                 if is_edit {
                     self.manufacture_to_edit.date = d;
@@ -310,6 +187,10 @@ impl ManufactureState {
                 if self.add_manufacture {
                     self.add_manufacture = false;
                 } else {
+                    if self.edit_manufacture {
+                        self.edit_manufacture = false;
+                        self.products_to_add = Vec::new();
+                    }
                     self.add_manufacture = true;
                 }
             }
@@ -354,14 +235,39 @@ impl ManufactureState {
 
                 self.products_to_add = f_products.collect();
             }
-            ManufactureMessage::Submit(is_edit) => match is_edit {
-                true => {
-                    self.edit_manufacture = false;
+            ManufactureMessage::Submit(is_edit) => {
+                let override_shortage = self.allow_backorder && !self.shortages().is_empty();
+                match is_edit {
+                    true => {
+                        if !override_shortage {
+                            self.manufacture_to_edit.backorder_reason = None;
+                        }
+                        self.edit_manufacture = false;
+                    }
+                    false => {
+                        if !override_shortage {
+                            self.manufacture_to_add.backorder_reason = None;
+                        }
+                        self.add_manufacture = false;
+                    }
                 }
-                false => {
-                    self.add_manufacture = false;
+                self.allow_backorder = false;
+            }
+            ManufactureMessage::AllowBackorder(b) => {
+                self.allow_backorder = b;
+            }
+            ManufactureMessage::BackorderReason(r) => {
+                let manufacture_reason = if self.edit_manufacture {
+                    &mut self.manufacture_to_edit.backorder_reason
+                } else {
+                    &mut self.manufacture_to_add.backorder_reason
+                };
+                if r.chars().count() == 0 {
+                    *manufacture_reason = None;
+                } else {
+                    *manufacture_reason = Some(r);
                 }
-            },
+            }
             ManufactureMessage::Delete => {
                 self.edit_manufacture = false;
             }
@@ -399,6 +305,12 @@ impl ManufactureState {
                             "Add Manufacture",
                             AppMessage::Manufacture(ManufactureMessage::ShowAddManufacture),
                         ))
+                        .push(export_row(
+                            &self.export_path,
+                            |input| AppMessage::Manufacture(ManufactureMessage::ExportPath(input)),
+                            AppMessage::Manufacture(ManufactureMessage::Export),
+                        ))
+                        .spacing(12)
                         .padding(12),
                 )
                 .push_maybe(self.create_view())
@@ -430,6 +342,10 @@ impl ManufactureState {
 
     pub fn create_view(&self) -> Option<Element<AppMessage>> {
         if self.add_manufacture {
+            let shortages = self.shortages();
+            let can_submit = shortages.is_empty()
+                || (self.allow_backorder && self.manufacture_to_add.backorder_reason.is_some());
+
             Some(
                 Column::new()
                     .max_width(1000)
@@ -464,11 +380,34 @@ impl ManufactureState {
                                                 .push(self.selected_products()),
                                         ),
                                 )
+                                .push_maybe(shortage_view(
+                                    &shortages,
+                                    self.allow_backorder,
+                                    &self
+                                        .manufacture_to_add
+                                        .backorder_reason
+                                        .clone()
+                                        .unwrap_or("".to_string()),
+                                    |b| {
+                                        AppMessage::Manufacture(ManufactureMessage::AllowBackorder(
+                                            b,
+                                        ))
+                                    },
+                                    |input| {
+                                        AppMessage::Manufacture(
+                                            ManufactureMessage::BackorderReason(input),
+                                        )
+                                    },
+                                ))
                                 .push(
                                     Button::new("Submit")
-                                        .on_press(AppMessage::Manufacture(
-                                            ManufactureMessage::Submit(false),
-                                        ))
+                                        .on_press_maybe(if can_submit {
+                                            Some(AppMessage::Manufacture(
+                                                ManufactureMessage::Submit(false),
+                                            ))
+                                        } else {
+                                            None
+                                        })
                                         .style(CustomMainButtonStyle),
                                 ),
                         )
@@ -483,6 +422,10 @@ impl ManufactureState {
 
     pub fn edit_view(&self) -> Option<Element<AppMessage>> {
         if self.edit_manufacture {
+            let shortages = self.shortages();
+            let can_submit = shortages.is_empty()
+                || (self.allow_backorder && self.manufacture_to_edit.backorder_reason.is_some());
+
             Some(
                 Container::new(
                     Column::new()
@@ -490,7 +433,8 @@ impl ManufactureState {
                         .align_items(Alignment::Center)
                         .push(
                             Column::new()
-                                .max_width(700)
+                                .max_width(1000)
+                                .spacing(12)
                                 .push(
                                     Text::new("Edit Manufacture".to_string())
                                         .size(24)
@@ -505,7 +449,32 @@ impl ManufactureState {
                                             input, true,
                                         ))
                                     },
-                                    Some(AppMessage::Manufacture(ManufactureMessage::Submit(true))),
+                                    None,
+                                ))
+                                .push(
+                                    Row::new()
+                                        .spacing(12)
+                                        .push(self.select_product())
+                                        .push(self.selected_products()),
+                                )
+                                .push_maybe(shortage_view(
+                                    &shortages,
+                                    self.allow_backorder,
+                                    &self
+                                        .manufacture_to_edit
+                                        .backorder_reason
+                                        .clone()
+                                        .unwrap_or("".to_string()),
+                                    |b| {
+                                        AppMessage::Manufacture(ManufactureMessage::AllowBackorder(
+                                            b,
+                                        ))
+                                    },
+                                    |input| {
+                                        AppMessage::Manufacture(
+                                            ManufactureMessage::BackorderReason(input),
+                                        )
+                                    },
                                 ))
                                 .push(
                                     Row::new()
@@ -514,9 +483,13 @@ impl ManufactureState {
                                                 Text::new("Submit".to_string())
                                                     .horizontal_alignment(Horizontal::Center),
                                             )
-                                            .on_press(AppMessage::Manufacture(
-                                                ManufactureMessage::Submit(true),
-                                            ))
+                                            .on_press_maybe(if can_submit {
+                                                Some(AppMessage::Manufacture(
+                                                    ManufactureMessage::Submit(true),
+                                                ))
+                                            } else {
+                                                None
+                                            })
                                             .style(CustomMainButtonStyle)
                                             .width(Length::Fill),
                                         )
@@ -1,39 +1,22 @@
-use std::env;
-
 use iced::{
     alignment::Horizontal,
     widget::{Button, Column, Container, Row, Scrollable, Text},
     Alignment, Element, Length,
 };
-use sqlx::SqlitePool;
 
 use crate::{
     components::{
-        add_button, layout, table_column, table_header, table_row_qty_style, table_style,
-        text_input_column, CustomButtonStyle, CustomMainButtonStyle,
+        add_button, export_row, layout, table_column, table_header, table_row_qty_style,
+        table_style, text_input_column, CustomButtonStyle, CustomMainButtonStyle,
     },
-    error::Errorr,
+    repo::part::{Part, PartToAdd},
     AppMessage,
 };
 
-#[derive(Default, Clone, Debug)]
-pub struct Part {
-    pub part_id: i64,
-    pub name: String,
-    pub units_left: i64,
-    pub cost: f64,
-    pub total_spent: f64,
-    pub total_units_purchased: i64,
-}
-
-#[derive(Default, Clone, Debug)]
-pub struct PartToAdd {
-    pub name: String,
-}
-
 #[derive(Default, Clone)]
 pub struct PartsState {
     pub parts: Vec<Part>,
+    pub export_path: String,
     pub part_to_add: PartToAdd,
     add_part: bool,
     pub part_to_edit: Part,
@@ -43,81 +26,22 @@ pub struct PartsState {
 #[derive(Clone, Debug, PartialEq)]
 pub enum PartsMessage {
     NameInput(String, bool),
+    ReorderPointInput(String),
+    ReorderQtyInput(String),
     ShowAddPart,
     Submit(bool),
     Delete,
-}
-
-pub async fn get_parts() -> Result<Vec<Part>, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let parts = sqlx::query_as!(Part, "SELECT * FROM Part")
-        .fetch_all(&pool)
-        .await?;
-
-    Ok(parts)
+    ExportPath(String),
+    Export,
 }
 
 impl PartsState {
-    pub async fn add_part(part: PartToAdd) -> Result<(), Errorr> {
-        let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-        let name = part.name;
-
-        sqlx::query!(
-            "
-            INSERT INTO Part (name) 
-            VALUES (?)
-            ",
-            name,
-        )
-        .execute(&pool)
-        .await?;
-
-        Ok(())
-    }
-
-    pub async fn edit_part(part: Part) -> Result<(), Errorr> {
-        let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-        let id = part.part_id;
-        let name = part.name.as_str();
-
-        sqlx::query!(
-            "
-            UPDATE Part
-            SET name = ?
-            WHERE part_id = ?
-            ",
-            name,
-            id
-        )
-        .execute(&pool)
-        .await?;
-
-        Ok(())
-    }
-
-    pub async fn delete_part(part: Part) -> Result<(), Errorr> {
-        let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-        let id = part.part_id;
-
-        sqlx::query!(
-            "
-            DELETE FROM Part
-            WHERE part_id = ?
-            ",
-            id
-        )
-        .execute(&pool)
-        .await?;
-
-        Ok(())
-    }
-
     pub fn update(&mut self, message: PartsMessage) {
         match message {
+            PartsMessage::ExportPath(p) => {
+                self.export_path = p;
+            }
+            PartsMessage::Export => {}
             PartsMessage::NameInput(s, is_edit) => {
                 if is_edit {
                     self.part_to_edit.name = s;
@@ -125,6 +49,12 @@ impl PartsState {
                     self.part_to_add.name = s;
                 }
             }
+            PartsMessage::ReorderPointInput(s) => {
+                self.part_to_edit.reorder_point = s.parse::<i64>().unwrap_or(0);
+            }
+            PartsMessage::ReorderQtyInput(s) => {
+                self.part_to_edit.reorder_qty = s.parse::<i64>().ok();
+            }
             PartsMessage::ShowAddPart => {
                 if self.add_part {
                     self.add_part = false;
@@ -159,6 +89,12 @@ impl PartsState {
                             "Add Part",
                             AppMessage::Parts(PartsMessage::ShowAddPart),
                         ))
+                        .push(export_row(
+                            &self.export_path,
+                            |input| AppMessage::Parts(PartsMessage::ExportPath(input)),
+                            AppMessage::Parts(PartsMessage::Export),
+                        ))
+                        .spacing(12)
                         .padding(12),
                 )
                 .push_maybe(self.create_view())
@@ -189,7 +125,9 @@ impl PartsState {
                                                 &item.total_units_purchased.to_string(),
                                             )),
                                     )
-                                    .style(table_row_qty_style(item.units_left)),
+                                    .style(
+                                        table_row_qty_style(item.units_left, item.reorder_point),
+                                    ),
                                 )
                                 .style(CustomButtonStyle)
                                 .on_press(AppMessage::EditPart(item.clone()))
@@ -261,6 +199,22 @@ impl PartsState {
                                 |input| AppMessage::Parts(PartsMessage::NameInput(input, true)),
                                 Some(AppMessage::Parts(PartsMessage::Submit(true))),
                             ))
+                            .push(text_input_column(
+                                "Reorder Point",
+                                &self.part_to_edit.reorder_point.to_string(),
+                                |input| AppMessage::Parts(PartsMessage::ReorderPointInput(input)),
+                                Some(AppMessage::Parts(PartsMessage::Submit(true))),
+                            ))
+                            .push(text_input_column(
+                                "Reorder Qty",
+                                &self
+                                    .part_to_edit
+                                    .reorder_qty
+                                    .map(|q| q.to_string())
+                                    .unwrap_or_default(),
+                                |input| AppMessage::Parts(PartsMessage::ReorderQtyInput(input)),
+                                Some(AppMessage::Parts(PartsMessage::Submit(true))),
+                            ))
                             .push(
                                 Row::new()
                                     .push(
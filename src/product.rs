@@ -1,51 +1,26 @@
-use std::env;
-
 use iced::{
     alignment::Horizontal,
     widget::{Button, Column, Container, Row, Scrollable, Text, TextInput},
     Alignment, Element, Length,
 };
-use sqlx::SqlitePool;
 
 use crate::{
     components::{
-        add_button, bold_text, card_style, close_button, close_edit_row, layout, table_column, table_header, table_row_qty_style, table_row_style, table_style, text_input_column, CustomButtonStyle, CustomMainButtonStyle
+        add_button, bold_text, card_style, close_button, close_edit_row, export_row, layout, table_column, table_header, table_row_qty_style, table_row_style, table_style, text_input_column, CustomButtonStyle, CustomMainButtonStyle
     },
-    error::Errorr,
     manufacture::select_header,
-    parts::Part,
-    purchase::{parse_input, validate_input, PartToSelect},
+    purchase::{parse_input, validate_input},
+    repo::{
+        part::{Part, PartToSelect},
+        product::{Product, ProductPart, ProductToAdd},
+    },
     AppMessage,
 };
 
-#[derive(Debug, Default, Clone)]
-pub struct Product {
-    pub product_id: i64,
-    pub name: String,
-    pub units: i64,
-    pub cost: f64,
-    pub msrp: f64,
-}
-
-#[derive(Debug, Default, Clone)]
-pub struct ProductToAdd {
-    pub name: String,
-    pub msrp: String,
-}
-
-#[derive(Debug, Default, Clone)]
-pub struct ProductPart {
-    pub id: i64,
-    pub name: String,
-    pub qty: i64,
-    pub cost: f64,
-    pub part_id: i64,
-    pub product_id: i64,
-}
-
 #[derive(Default, Clone)]
 pub struct ProductState {
     pub products: Vec<Product>,
+    pub export_path: String,
     pub product_to_add: ProductToAdd,
     pub product_to_edit: Product,
     show_add_product: bool,
@@ -66,6 +41,8 @@ pub struct ProductState {
 pub enum ProductMessage {
     NameInput(String, bool),
     MsrpInput(String, bool),
+    ReorderPointInput(String),
+    ReorderQtyInput(String),
     Submit(bool),
     ShowAddProduct,
     Delete,
@@ -76,35 +53,8 @@ pub enum ProductMessage {
     RemovePart(i64),
     Query(String),
     CloseView,
-}
-
-pub async fn get_products() -> Result<Vec<Product>, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let products = sqlx::query_as!(Product, "SELECT * FROM Product")
-        .fetch_all(&pool)
-        .await?;
-
-    Ok(products)
-}
-
-pub async fn get_product_parts(product_id: i64) -> Result<Vec<ProductPart>, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let products = sqlx::query_as!(
-        ProductPart,
-        "SELECT ProductPart.id, ProductPart.qty, ProductPart.part_id, ProductPart.product_id, 
-                                   Part.name as name, Part.cost as cost
-                                   FROM ProductPart
-                                   JOIN Part ON ProductPart.part_id = Part.part_id
-                                   WHERE ProductPart.product_id = ?
-                                   ",
-        product_id
-    )
-    .fetch_all(&pool)
-    .await?;
-
-    Ok(products)
+    ExportPath(String),
+    Export,
 }
 
 fn part_view_row(label: &str, value: String) -> Row<'static, AppMessage> {
@@ -135,98 +85,15 @@ fn part_view(part: &ProductPart) -> Container<'static, AppMessage> {
     .style(card_style())
 }
 
-pub async fn add_product(
-    product: ProductToAdd,
-    parts_to_add: Vec<PartToSelect>,
-    ) -> Result<(), Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let name = product.name;
-    let msrp = product.msrp;
-    let mut cost = 0.00;
-
-    for part in &parts_to_add {
-        cost += part.cost.parse::<f64>().unwrap_or(0.00) * part.qty as f64;
-    }
-
-    let r = sqlx::query!(
-        "
-        INSERT INTO Product (name, msrp, cost)
-        VALUES (?,?,?)
-        ",
-        name,
-        msrp,
-        cost
-        )
-        .execute(&pool)
-        .await?;
-
-    let product_id = r.last_insert_rowid();
-
-    for part in &parts_to_add {
-        sqlx::query!(
-            "
-            INSERT INTO ProductPart (qty, cost, product_id, part_id)
-            VALUES (?,?,?,?)
-            ",
-            part.qty,
-            part.cost,
-            product_id,
-            part.part_id
-            )
-            .execute(&pool)
-            .await?;
+impl ProductState {
+    // What the product will cost once saved, derived from the selected parts.
+    fn parts_cost(&self) -> f64 {
+        self.parts_to_add
+            .iter()
+            .map(|part| part.cost.parse::<f64>().unwrap_or(0.00) * part.qty as f64)
+            .sum()
     }
 
-    Ok(())
-}
-
-pub async fn edit_product(product: Product) -> Result<(), Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let id = product.product_id;
-    let name = product.name.as_str();
-    let units = product.units;
-    let cost = product.cost;
-    let msrp = product.msrp;
-
-    sqlx::query!(
-        "
-        UPDATE Product
-        SET name = ?, units = ?, cost = ?, msrp = ?
-        WHERE product_id = ?
-        ",
-        name,
-        units,
-        cost,
-        msrp,
-        id
-        )
-        .execute(&pool)
-        .await?;
-
-    Ok(())
-}
-
-pub async fn delete_product(product: Product) -> Result<(), Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let id = product.product_id;
-
-    sqlx::query!(
-        "
-        DELETE FROM Product
-        WHERE product_id = ?
-        ",
-        id
-        )
-        .execute(&pool)
-        .await?;
-
-    Ok(())
-}
-
-impl ProductState {
     fn select_part(&self) -> Container<'_, AppMessage> {
         Container::new(
             Column::new()
@@ -335,6 +202,10 @@ impl ProductState {
 
     pub fn update(&mut self, message: ProductMessage) {
         match message {
+            ProductMessage::ExportPath(p) => {
+                self.export_path = p;
+            }
+            ProductMessage::Export => {}
             ProductMessage::NameInput(input, is_edit) => {
                 if is_edit {
                     self.product_to_edit.name = input;
@@ -352,6 +223,12 @@ impl ProductState {
                     }
                 }
             }
+            ProductMessage::ReorderPointInput(input) => {
+                self.product_to_edit.reorder_point = input.parse::<i64>().unwrap_or(0);
+            }
+            ProductMessage::ReorderQtyInput(input) => {
+                self.product_to_edit.reorder_qty = input.parse::<i64>().ok();
+            }
             ProductMessage::Submit(is_edit) => {
                 if is_edit {
                     self.edit_product = false;
@@ -363,6 +240,10 @@ impl ProductState {
                 if self.show_add_product {
                     self.show_add_product = false;
                 } else {
+                    if self.edit_product {
+                        self.edit_product = false;
+                        self.parts_to_add = Vec::new();
+                    }
                     self.view_product = false;
                     self.show_add_product = true;
                 }
@@ -459,6 +340,12 @@ impl ProductState {
                             "Add Product",
                             AppMessage::Product(ProductMessage::ShowAddProduct),
                         ))
+                        .push(export_row(
+                            &self.export_path,
+                            |input| AppMessage::Product(ProductMessage::ExportPath(input)),
+                            AppMessage::Product(ProductMessage::Export),
+                        ))
+                        .spacing(12)
                         .padding(12),
                 )
                 .push_maybe(self.create_view())
@@ -496,7 +383,7 @@ impl ProductState {
                                                     .as_str(),
                                                 )),
                                         )
-                                        .style(table_row_qty_style(product.units)),
+                                        .style(table_row_qty_style(product.units, product.reorder_point)),
                                     )
                                     .style(CustomButtonStyle)
                                     .on_press(AppMessage::ViewProduct(product.clone()))
@@ -576,7 +463,7 @@ impl ProductState {
                         .push(
                             Column::new()
                                 .spacing(8)
-                                .max_width(700)
+                                .max_width(1000)
                                 .push(
                                     Text::new("Edit Product".to_string())
                                         .size(24)
@@ -599,6 +486,36 @@ impl ProductState {
                                     },
                                     Some(AppMessage::Product(ProductMessage::Submit(true))),
                                 ))
+                                .push(text_input_column(
+                                    "Reorder Point",
+                                    &self.product_to_edit.reorder_point.to_string(),
+                                    |input| {
+                                        AppMessage::Product(ProductMessage::ReorderPointInput(input))
+                                    },
+                                    Some(AppMessage::Product(ProductMessage::Submit(true))),
+                                ))
+                                .push(text_input_column(
+                                    "Reorder Qty",
+                                    &self
+                                        .product_to_edit
+                                        .reorder_qty
+                                        .map(|q| q.to_string())
+                                        .unwrap_or_default(),
+                                    |input| {
+                                        AppMessage::Product(ProductMessage::ReorderQtyInput(input))
+                                    },
+                                    Some(AppMessage::Product(ProductMessage::Submit(true))),
+                                ))
+                                .push(
+                                    Row::new()
+                                        .spacing(12)
+                                        .push(self.select_part())
+                                        .push(self.selected_parts()),
+                                )
+                                .push(part_view_row(
+                                    "Cost: ",
+                                    format!("${:.2}", self.parts_cost()),
+                                ))
                                 .push(
                                     Row::new()
                                         .push(
@@ -1,55 +1,21 @@
-use std::env;
-
 use iced::{
     alignment::{Horizontal, Vertical},
     widget::{Button, Column, Container, Row, Scrollable, Text, TextInput},
     Alignment, Element, Length,
 };
 use regex::Regex;
-use sqlx::SqlitePool;
 
 use crate::{
     components::{
-        add_button, bold_text, card_style, close_button, close_edit_row, layout, table_column, table_header, table_row_style, table_style, text_input_column, CustomButtonStyle, CustomMainButtonStyle
+        add_button, bold_text, card_style, close_button, close_edit_row, export_row, layout, table_column, table_header, table_row_style, table_style, text_input_column, CustomButtonStyle, CustomMainButtonStyle
+    },
+    repo::{
+        part::{Part, PartToSelect},
+        purchase::{Purchase, PurchasePart, PurchaseToAdd},
     },
-    error::Errorr,
-    parts::Part,
     AppMessage,
 };
 
-#[derive(Default, Clone, Debug)]
-pub struct Purchase {
-    pub id: i64,
-    pub date: String,
-    pub total: f64,
-    pub note: Option<String>,
-}
-
-#[derive(Default, Clone, Debug)]
-pub struct PurchasePart {
-    pub id: i64,
-    name: String,
-    qty: i64,
-    cost: f64,
-}
-
-#[derive(Default, Clone, Debug)]
-pub struct PurchaseToAdd {
-    pub date: String,
-    pub total: f64,
-    pub note: Option<String>,
-}
-
-#[derive(Default, Clone, Debug)]
-pub struct PartToSelect {
-    pub part_id: i64,
-    pub name: String,
-    pub cost: String,
-    pub qty: i64,
-    pub total_spent: f64,
-    pub total_units_purchased: i64,
-}
-
 #[derive(Default, Clone, Debug)]
 pub struct PartToCreate {
     pub name: String,
@@ -58,6 +24,7 @@ pub struct PartToCreate {
 #[derive(Default, Clone)]
 pub struct PurchaseState {
     pub purchases: Vec<Purchase>,
+    pub export_path: String,
     pub purchase_to_add: PurchaseToAdd,
     add_purchase: bool,
     pub purchase_to_edit: Purchase,
@@ -89,6 +56,8 @@ pub enum PurchaseMessage {
     Delete,
     Query(String),
     CloseView,
+    ExportPath(String),
+    Export,
 }
 
 fn select_part_header() -> Container<'static, AppMessage> {
@@ -103,53 +72,6 @@ fn select_part_header() -> Container<'static, AppMessage> {
     .style(table_row_style())
 }
 
-pub async fn get_purchases() -> Result<Vec<Purchase>, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let purchases = sqlx::query_as!(Purchase, "SELECT * FROM Purchase")
-        .fetch_all(&pool)
-        .await?;
-
-    Ok(purchases)
-}
-
-pub async fn get_purchase_parts(purchase_id: i64) -> Result<Vec<PurchasePart>, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let purchases = sqlx::query_as!(
-        PurchasePart,
-        "SELECT PurchasePart.qty, PurchasePart.cost, PurchasePart.id,
-                                   Part.name as name
-                                   FROM PurchasePart
-                                   JOIN Part ON PurchasePart.part_id = Part.part_id
-                                   WHERE PurchasePart.purchase_id = ?
-                                   ",
-        purchase_id
-    )
-    .fetch_all(&pool)
-    .await?;
-
-    Ok(purchases)
-}
-
-pub async fn delete_purchase(purchase: Purchase) -> Result<(), Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let id = purchase.id;
-
-    sqlx::query!(
-        "
-        DELETE FROM Purchase
-        WHERE id = ?
-        ",
-        id
-        )
-        .execute(&pool)
-        .await?;
-
-    Ok(())
-}
-
 fn part_view_row(label: &str, value: String) -> Row<'static, AppMessage> {
     Row::new()
         .padding(4)
@@ -192,121 +114,12 @@ pub fn validate_input(s: &str) -> bool {
 }
 
 impl PurchaseState {
-    pub async fn add_purchase(
-        parts_to_add: Vec<PartToSelect>,
-        purchase_to_add: PurchaseToAdd,
-        parts: Vec<Part>,
-    ) -> Result<(), Errorr> {
-        let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-        let date = purchase_to_add.date;
-        let total = purchase_to_add.total;
-        let note = purchase_to_add.note;
-
-        let r = sqlx::query!(
-            "
-            INSERT INTO Purchase (total, date, note)
-            VALUES (?,?,?)
-            ",
-            total,
-            date,
-            note
-        )
-        .execute(&pool)
-        .await?;
-
-        let purchase_id = r.last_insert_rowid();
-
-        for part in &parts_to_add {
-            sqlx::query!(
-                "
-                INSERT INTO PurchasePart (qty, cost, purchase_id, part_id)
-                VALUES (?,?,?,?)
-                ",
-                part.qty,
-                part.cost,
-                purchase_id,
-                part.part_id
-            )
-            .execute(&pool)
-            .await?;
-
-            let partt = parts.iter().find(|p| p.part_id == part.part_id).unwrap();
-            let total_units = partt.total_units_purchased + part.qty;
-            let total_spent = partt.total_spent + part.cost.parse::<f64>().unwrap_or(0.00);
-            let units_left = partt.units_left + part.qty;
-            let cost = total_spent / total_units as f64;
-
-            sqlx::query!(
-                "
-                UPDATE Part
-                SET total_units_purchased = ?, total_spent = ?, units_left = ?, cost = ? 
-                WHERE part_id = ?
-                ",
-                total_units,
-                total_spent,
-                units_left,
-                cost,
-                part.part_id
-            )
-            .execute(&pool)
-            .await?;
-
-            sqlx::query!(
-                "
-                UPDATE ProductPart
-                SET cost = ?
-                WHERE part_id = ?
-                ",
-                cost,
-                part.part_id
-            )
-            .execute(&pool)
-            .await?;
-        }
-
-        let products = sqlx::query!(
-            "
-            SELECT * FROM Product
-            "
-        )
-        .fetch_all(&pool)
-        .await?;
-
-        for product in &products {
-            let product_parts = sqlx::query!(
-                "
-                SELECT * FROM ProductPart
-                WHERE product_id = ?  
-                ",
-                product.product_id
-            )
-            .fetch_all(&pool)
-            .await?;
-
-            let mut cost = 0.00;
-            for pp in &product_parts {
-                cost += pp.cost * pp.qty as f64;
-            }
-
-            sqlx::query!(
-                "
-                UPDATE Product
-                SET cost = ? 
-                WHERE product_id = ?
-                ",
-                cost,
-                product.product_id
-            )
-            .execute(&pool)
-            .await?;
-        }
-
-        Ok(())
-    }
-
     pub fn update(&mut self, message: PurchaseMessage) {
         match message {
+            PurchaseMessage::ExportPath(p) => {
+                self.export_path = p;
+            }
+            PurchaseMessage::Export => {}
             PurchaseMessage::DateInput(d, is_edit) => {
                 if is_edit {
                     self.purchase_to_edit.date = d;
@@ -325,6 +138,10 @@ impl PurchaseState {
                 if self.add_purchase {
                     self.add_purchase = false;
                 } else {
+                    if self.edit_purchase {
+                        self.edit_purchase = false;
+                        self.parts_to_add = Vec::new();
+                    }
                     self.view_purchase = false;
                     self.add_purchase = true;
                 }
@@ -410,12 +227,18 @@ impl PurchaseState {
                 self.create_part = false;
             }
             PurchaseMessage::Submit(is_edit) => {
+                let total = self
+                    .parts_to_add
+                    .iter()
+                    .map(|x| x.cost.parse::<f64>().unwrap_or(0.00))
+                    .sum();
+
                 if is_edit {
+                    self.purchase_to_edit.total = total;
                     self.edit_purchase = false;
+                    self.view_purchase = false;
                 } else {
-                    for x in &self.parts_to_add {
-                        self.purchase_to_add.total += x.cost.parse::<f64>().unwrap_or(0.00);
-                    }
+                    self.purchase_to_add.total = total;
                     self.add_purchase = false;
                 }
             }
@@ -460,6 +283,12 @@ impl PurchaseState {
                             "Add Purchase",
                             AppMessage::Purchase(PurchaseMessage::ShowAddPurchase),
                         ))
+                        .push(export_row(
+                            &self.export_path,
+                            |input| AppMessage::Purchase(PurchaseMessage::ExportPath(input)),
+                            AppMessage::Purchase(PurchaseMessage::Export),
+                        ))
+                        .spacing(12)
                         .padding(12),
                 )
                 .push_maybe(self.view_purchase())
@@ -701,7 +530,7 @@ impl PurchaseState {
                         .align_items(Alignment::Center)
                         .push(
                             Column::new()
-                                .max_width(700)
+                                .max_width(1000)
                                 .push(
                                     Text::new("Edit Purchase".to_string())
                                         .size(24)
@@ -718,6 +547,23 @@ impl PurchaseState {
                                     },
                                     Some(AppMessage::Purchase(PurchaseMessage::Submit(true))),
                                 ))
+                                .push(text_input_column(
+                                    "Note",
+                                    &self.purchase_to_edit.note.clone().unwrap_or("".to_string()),
+                                    |input| {
+                                        AppMessage::Purchase(PurchaseMessage::NoteInput(
+                                            input, true,
+                                        ))
+                                    },
+                                    None,
+                                ))
+                                .push(
+                                    Row::new()
+                                        .padding([12, 0, 12, 0])
+                                        .spacing(12)
+                                        .push(self.select_part())
+                                        .push(self.selected_parts()),
+                                )
                                 .push(
                                     Row::new()
                                         .push(
@@ -0,0 +1,152 @@
+use iced::{
+    widget::{Button, Checkbox, Column, Container, Row, Scrollable, Text},
+    Alignment, Element, Length,
+};
+
+use crate::{
+    components::{
+        bold_text, card_style, error_text, layout, table_column, table_header, table_row_style,
+        table_style, CustomButtonStyle,
+    },
+    repo::{money, payment::Receivable},
+    AppMessage,
+};
+
+#[derive(Default, Clone)]
+pub struct ReceivablesState {
+    pub receivables: Vec<Receivable>,
+    overdue_only: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum ReceivablesMessage {
+    OverdueOnly(bool),
+    OpenSale(i64),
+}
+
+fn total_view(label: &str, value: f64) -> Container<'static, AppMessage> {
+    Container::new(
+        Column::new()
+            .align_items(Alignment::Center)
+            .push(Text::new(label.to_string()))
+            .push(Text::new(money(value)).size(24)),
+    )
+    .padding(12)
+    .width(180)
+    .style(card_style())
+}
+
+fn receivable_row<'a>(r: &Receivable) -> Element<'a, AppMessage> {
+    Button::new(
+        Container::new(
+            Row::new()
+                .padding(10)
+                .push(table_column(&format!("#{}", r.sale_id)))
+                .push(table_column(&r.date))
+                .push(table_column(&r.due_date.clone().unwrap_or_default()))
+                .push(table_column(&money(r.total)))
+                .push(table_column(&money(r.paid)))
+                .push(table_column(&money(r.balance())))
+                .push(
+                    Column::new()
+                        .width(150)
+                        .align_items(Alignment::Center)
+                        .push(if r.overdue {
+                            error_text("Overdue")
+                        } else {
+                            Text::new("")
+                        }),
+                ),
+        )
+        .style(table_row_style()),
+    )
+    .style(CustomButtonStyle)
+    .on_press(AppMessage::Receivables(ReceivablesMessage::OpenSale(
+        r.sale_id,
+    )))
+    .into()
+}
+
+impl ReceivablesState {
+    pub fn update(&mut self, message: ReceivablesMessage) {
+        match message {
+            ReceivablesMessage::OverdueOnly(b) => {
+                self.overdue_only = b;
+            }
+            ReceivablesMessage::OpenSale(_) => {}
+        }
+    }
+
+    // Rows arrive ordered by client, so each client's sales are consecutive.
+    fn client_groups(&self) -> Vec<(&str, Vec<&Receivable>)> {
+        let mut groups: Vec<(&str, Vec<&Receivable>)> = Vec::new();
+
+        for r in self
+            .receivables
+            .iter()
+            .filter(|r| r.overdue || !self.overdue_only)
+        {
+            match groups.last_mut() {
+                Some((_, rows)) if rows[0].client_id == r.client_id => rows.push(r),
+                _ => groups.push((&r.client_name, vec![r])),
+            }
+        }
+
+        groups
+    }
+
+    pub fn view(&self) -> Element<'_, AppMessage> {
+        let outstanding: f64 = self.receivables.iter().map(|r| r.balance()).sum();
+        let overdue: f64 = self
+            .receivables
+            .iter()
+            .filter(|r| r.overdue)
+            .map(|r| r.balance())
+            .sum();
+
+        layout(
+            Column::new()
+                .width(Length::Fill)
+                .padding([12, 0, 0, 12])
+                .spacing(12)
+                .align_items(Alignment::Center)
+                .push(Text::new("Receivables".to_string()).size(24))
+                .push(
+                    Row::new()
+                        .spacing(12)
+                        .push(total_view("Outstanding", outstanding))
+                        .push(total_view("Overdue", overdue)),
+                )
+                .push(
+                    Checkbox::new("Overdue only", self.overdue_only)
+                        .on_toggle(|b| AppMessage::Receivables(ReceivablesMessage::OverdueOnly(b))),
+                )
+                .push(
+                    Container::new(
+                        table_header(&["Sale", "Date", "Due", "Total", "Paid", "Balance", ""])
+                            .push(Scrollable::new(Column::new().extend(
+                                self.client_groups().into_iter().map(|(client, rows)| {
+                                    let balance: f64 = rows.iter().map(|r| r.balance()).sum();
+                                    Column::new()
+                                        .push(
+                                            Row::new()
+                                                .padding(10)
+                                                .spacing(12)
+                                                .push(bold_text(client))
+                                                .push(Text::new(format!(
+                                                    "{} owed",
+                                                    money(balance)
+                                                ))),
+                                        )
+                                        .extend(rows.into_iter().map(receivable_row))
+                                        .into()
+                                }),
+                            ))),
+                    )
+                    .style(table_style()),
+                )
+                .into(),
+        )
+        .into()
+    }
+}
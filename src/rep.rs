@@ -1,35 +1,29 @@
-use std::env;
-
 use iced::{
     alignment::Horizontal,
     widget::{Button, Column, Container, Row, Scrollable, Text},
     Alignment, Element, Length,
 };
-use sqlx::SqlitePool;
 
 use crate::{
     components::{
-        add_button, layout, table_column, table_header, table_row_style, table_style,
-        text_input_column, CustomButtonStyle, CustomMainButtonStyle,
+        add_button, bold_text, export_row, layout, table_column, table_header, table_row_style,
+        table_style, text_input_column, CustomButtonStyle, CustomMainButtonStyle,
     },
-    error::Errorr,
+    repo::rep::{Rep, RepStatement, StatementRange},
     AppMessage,
 };
 
-#[derive(Default, Clone, Debug)]
-pub struct Rep {
-    pub id: i64,
-    pub name: String,
-    pub percentage: u8,
-}
-
 #[derive(Default, Clone)]
 pub struct RepState {
     pub reps: Vec<Rep>,
+    pub export_path: String,
     add_rep: bool,
     pub rep_to_add: Rep,
     pub edit_rep: bool,
     pub rep_to_edit: Rep,
+    pub statement: RepStatement,
+    pub statement_range: StatementRange,
+    pub payout_date: String,
 }
 
 #[derive(Clone, Debug)]
@@ -39,99 +33,22 @@ pub enum RepMessage {
     Submit(bool),
     ShowAddRep,
     Delete,
-}
-
-pub async fn get_reps() -> Result<Vec<Rep>, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let reps = sqlx::query_as!(
-        Rep,
-        "SELECT id, name, percentage as `percentage: u8` FROM Rep"
-    )
-    .fetch_all(&pool)
-    .await?;
-
-    Ok(reps)
-}
-
-pub async fn get_rep(i: i64) -> Result<Rep, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let rep = sqlx::query_as!(
-        Rep,
-        "SELECT id, name, percentage as `percentage: u8` FROM Rep WHERE id = ?",
-        i
-    )
-    .fetch_one(&pool)
-    .await?;
-
-    Ok(rep)
-}
-
-pub async fn add_rep(rep: Rep) -> Result<(), Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let name = rep.name;
-    let percentage = rep.percentage;
-
-    sqlx::query!(
-        "
-        INSERT INTO Rep (name, percentage)
-        VALUES (?,?)
-        ",
-        name,
-        percentage
-    )
-    .execute(&pool)
-    .await?;
-
-    Ok(())
-}
-
-pub async fn edit_rep(rep: Rep) -> Result<(), Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let id = rep.id;
-    let name = rep.name;
-    let percentage = rep.percentage;
-
-    sqlx::query!(
-        "
-        UPDATE Rep 
-        SET name = ?, percentage = ?
-        WHERE id = ?
-        ",
-        name,
-        percentage,
-        id
-    )
-    .execute(&pool)
-    .await?;
-
-    Ok(())
-}
-
-pub async fn delete_rep(rep: Rep) -> Result<(), Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let id = rep.id;
-
-    sqlx::query!(
-        "
-        DELETE FROM Rep
-        WHERE id = ?
-        ",
-        id
-    )
-    .execute(&pool)
-    .await?;
-
-    Ok(())
+    StatementFrom(String),
+    StatementTo(String),
+    LoadStatement,
+    PayoutDate(String),
+    RecordPayout,
+    ExportPath(String),
+    Export,
 }
 
 impl RepState {
     pub fn update(&mut self, message: RepMessage) {
         match message {
+            RepMessage::ExportPath(p) => {
+                self.export_path = p;
+            }
+            RepMessage::Export => {}
             RepMessage::NameInput(s, is_edit) => {
                 if is_edit {
                     self.rep_to_edit.name = s;
@@ -163,9 +80,128 @@ impl RepState {
             RepMessage::Delete => {
                 self.edit_rep = false;
             }
+            RepMessage::StatementFrom(s) => {
+                self.statement_range.from = s;
+            }
+            RepMessage::StatementTo(s) => {
+                self.statement_range.to = s;
+            }
+            RepMessage::PayoutDate(s) => {
+                self.payout_date = s;
+            }
+            RepMessage::LoadStatement | RepMessage::RecordPayout => {}
         }
     }
 
+    fn statement_view(&self) -> Column<'_, AppMessage> {
+        let unpaid: f64 = self.statement.lines.iter().map(|l| l.rep_cut).sum::<f64>()
+            + self
+                .statement
+                .adjustments
+                .iter()
+                .map(|a| a.amount)
+                .sum::<f64>();
+
+        Column::new()
+            .spacing(8)
+            .push(bold_text("Commission Statement"))
+            .push(
+                Row::new()
+                    .spacing(12)
+                    .push(text_input_column(
+                        "From (YYYY-MM-DD)",
+                        &self.statement_range.from,
+                        |input| AppMessage::Rep(RepMessage::StatementFrom(input)),
+                        Some(AppMessage::Rep(RepMessage::LoadStatement)),
+                    ))
+                    .push(text_input_column(
+                        "To (YYYY-MM-DD)",
+                        &self.statement_range.to,
+                        |input| AppMessage::Rep(RepMessage::StatementTo(input)),
+                        Some(AppMessage::Rep(RepMessage::LoadStatement)),
+                    )),
+            )
+            .push(
+                Button::new("Load Statement")
+                    .on_press(AppMessage::Rep(RepMessage::LoadStatement))
+                    .style(CustomMainButtonStyle),
+            )
+            .push(
+                Container::new(
+                    table_header(&["Sale", "Date", "Total", "Commission"]).push(
+                        Scrollable::new(
+                            Column::new()
+                                .extend(self.statement.lines.iter().map(|line| {
+                                    Container::new(
+                                        Row::new()
+                                            .padding(10)
+                                            .push(table_column(&format!("#{}", line.sale_id)))
+                                            .push(table_column(&line.date))
+                                            .push(table_column(&format!("${:.2}", line.total)))
+                                            .push(table_column(&format!("${:.2}", line.rep_cut))),
+                                    )
+                                    .style(table_row_style())
+                                    .into()
+                                }))
+                                .extend(self.statement.adjustments.iter().map(|adjustment| {
+                                    let sale = match adjustment.sale_id {
+                                        Some(id) => format!("#{}", id),
+                                        None => String::from("Deleted sale"),
+                                    };
+                                    Container::new(
+                                        Row::new()
+                                            .padding(10)
+                                            .push(table_column(&sale))
+                                            .push(table_column(&adjustment.date))
+                                            .push(table_column("Adjustment"))
+                                            .push(table_column(&format!(
+                                                "${:.2}",
+                                                adjustment.amount
+                                            ))),
+                                    )
+                                    .style(table_row_style())
+                                    .into()
+                                })),
+                        )
+                        .height(Length::Shrink),
+                    ),
+                )
+                .max_height(300)
+                .style(table_style()),
+            )
+            .push(Text::new(format!("Unpaid: ${:.2}", unpaid)))
+            .push(
+                Row::new()
+                    .spacing(12)
+                    .align_items(Alignment::End)
+                    .push(text_input_column(
+                        "Payout Date (YYYY-MM-DD)",
+                        &self.payout_date,
+                        |input| AppMessage::Rep(RepMessage::PayoutDate(input)),
+                        None,
+                    ))
+                    .push(
+                        Button::new("Record Payout")
+                            .on_press_maybe(
+                                if self.statement.lines.is_empty()
+                                    && self.statement.adjustments.is_empty()
+                                {
+                                    None
+                                } else {
+                                    Some(AppMessage::Rep(RepMessage::RecordPayout))
+                                },
+                            )
+                            .style(CustomMainButtonStyle),
+                    ),
+            )
+            .push(bold_text("Payouts"))
+            .push(Column::new().spacing(4).extend(
+                self.statement.payouts.iter().map(|payout| {
+                    Text::new(format!("{}: ${:.2}", payout.date, payout.amount)).into()
+                }),
+            ))
+    }
+
     pub fn view(&self) -> Element<AppMessage> {
         layout(
             Column::new()
@@ -179,27 +215,34 @@ impl RepState {
                             "Add Rep",
                             AppMessage::Rep(RepMessage::ShowAddRep),
                         ))
+                        .push(export_row(
+                            &self.export_path,
+                            |input| AppMessage::Rep(RepMessage::ExportPath(input)),
+                            AppMessage::Rep(RepMessage::Export),
+                        ))
+                        .spacing(12)
                         .padding(12),
                 )
                 .push_maybe(self.create_view())
                 .push_maybe(self.edit_view())
                 .push(
-                    Container::new(table_header(&["Name", "Percentage"]).push(Scrollable::new(
-                        Column::new().extend(self.reps.iter().map(|rep| {
+                    Container::new(table_header(&["Name", "Percentage", "Unpaid"]).push(
+                        Scrollable::new(Column::new().extend(self.reps.iter().map(|rep| {
                             Button::new(
                                 Container::new(
                                     Row::new()
                                         .padding(10)
                                         .push(table_column(&rep.name))
-                                        .push(table_column(&format!("{}%", &rep.percentage))),
+                                        .push(table_column(&format!("{}%", &rep.percentage)))
+                                        .push(table_column(&format!("${:.2}", rep.balance))),
                                 )
                                 .style(table_row_style()),
                             )
                             .style(CustomButtonStyle)
                             .on_press(AppMessage::EditRep(rep.clone()))
                             .into()
-                        })),
-                    )))
+                        }))),
+                    ))
                     .style(table_style()),
                 )
                 .into(),
@@ -255,7 +298,8 @@ impl RepState {
                                         .width(Length::Fill)
                                         .style(iced::theme::Button::Destructive),
                                     ),
-                            ),
+                            )
+                            .push(self.statement_view()),
                     )
                     .into(),
             )
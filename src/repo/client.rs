@@ -0,0 +1,97 @@
+use sqlx::SqlitePool;
+
+use crate::error::Errorr;
+
+#[derive(Default, Clone, Debug)]
+pub struct Client {
+    pub client_id: i64,
+    pub name: String,
+    pub email: Option<String>,
+    pub address: String,
+}
+
+pub async fn get_clients(pool: SqlitePool) -> Result<Vec<Client>, Errorr> {
+    let clients = sqlx::query_as!(Client, "SELECT * FROM Client")
+        .fetch_all(&pool)
+        .await?;
+
+    Ok(clients)
+}
+
+pub async fn get_client(pool: SqlitePool, i: i64) -> Result<Client, Errorr> {
+    let client = sqlx::query_as!(Client, "SELECT * FROM Client WHERE client_id = ?", i)
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| match Errorr::from(e) {
+            Errorr::NotFound(_) => Errorr::NotFound(format!("Client #{} not found", i)),
+            e => e,
+        })?;
+
+    Ok(client)
+}
+
+pub async fn add_client(pool: SqlitePool, client: Client) -> Result<i64, Errorr> {
+    let name = client.name;
+    let address = client.address;
+    let email = client.email;
+
+    let c = sqlx::query!(
+        "
+        INSERT INTO Client (name, address, email)
+        VALUES (?,?,?)
+        ",
+        name,
+        address,
+        email,
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(c.last_insert_rowid())
+}
+
+pub async fn edit_client(pool: SqlitePool, client: Client) -> Result<(), Errorr> {
+    let id = client.client_id;
+    let name = client.name;
+    let address = client.address;
+    let email = client.email;
+
+    sqlx::query!(
+        "
+        UPDATE Client
+        SET name = ?, address = ?, email = ?
+        WHERE client_id = ?
+        ",
+        name,
+        address,
+        email,
+        id
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_client(pool: SqlitePool, client: Client) -> Result<(), Errorr> {
+    let id = client.client_id;
+
+    sqlx::query!(
+        "
+        DELETE FROM Client
+        WHERE client_id = ?
+        ",
+        id
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| match Errorr::from(e) {
+        Errorr::ConstraintViolation(_) => Errorr::ConstraintViolation(format!(
+            "{} can't be deleted while sales still reference them",
+            client.name
+        )),
+        e => e,
+    })?;
+
+    Ok(())
+}
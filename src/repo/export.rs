@@ -0,0 +1,372 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use sqlx::SqlitePool;
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    error::Errorr,
+    repo::{
+        client::Client,
+        manufacture::{get_manufacture_products, Manufacture},
+        part::Part,
+        product::{get_product_parts, Product},
+        purchase::{get_purchase_parts, Purchase},
+        rep::Rep,
+        sale::{discount_off, Sale},
+    },
+};
+
+// Text starting like a formula is quoted with ' so a spreadsheet shows it
+// instead of running it. Numbers, negative amounts included, go out as is.
+fn csv_field(s: &str) -> String {
+    let s = if s.starts_with(['=', '+', '-', '@']) && s.parse::<f64>().is_err() {
+        format!("'{}", s)
+    } else {
+        s.to_string()
+    };
+
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s
+    }
+}
+
+fn money(v: f64) -> String {
+    format!("{:.2}", v)
+}
+
+// A relative path goes in the user's Documents folder, or their home folder
+// without one, rather than wherever the app happened to be started from.
+fn export_path(path: &str) -> Result<PathBuf, Errorr> {
+    let path = Path::new(path.trim());
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .ok_or(Errorr::InvalidInput(String::from(
+            "Enter the full path of the file to export to",
+        )))?;
+    let documents = home.join("Documents");
+    let dir = if documents.is_dir() { documents } else { home };
+
+    Ok(dir.join(path))
+}
+
+// Writes the rows under a header line and returns how many rows went out and
+// where. An existing file is never overwritten.
+async fn write_csv(
+    path: &str,
+    header: &[&str],
+    rows: Vec<Vec<String>>,
+) -> Result<(usize, String), Errorr> {
+    if path.trim().is_empty() {
+        return Err(Errorr::InvalidInput(String::from(
+            "Enter a file path to export to",
+        )));
+    }
+    let path = export_path(path)?;
+
+    let mut out = header.join(",");
+    out.push('\n');
+    for row in &rows {
+        let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .await
+        .map_err(|e| match e.kind() {
+            ErrorKind::AlreadyExists => Errorr::InvalidInput(format!(
+                "{} already exists, pick another name",
+                path.display()
+            )),
+            _ => Errorr::from(e),
+        })?;
+    file.write_all(out.as_bytes()).await?;
+
+    Ok((rows.len(), path.display().to_string()))
+}
+
+// One row per line item, a sale without lines still gets a row. Only the sales
+// passed in are written, so the export matches what the screen shows.
+pub async fn export_sales(
+    pool: SqlitePool,
+    sales: Vec<Sale>,
+    path: String,
+) -> Result<(usize, String), Errorr> {
+    let mut rows = Vec::new();
+
+    for sale in &sales {
+        let lines = sqlx::query!(
+            "
+            SELECT Product.name, SaleProduct.qty, SaleProduct.msrp_at_sale, SaleProduct.cost_at_sale,
+            SaleProduct.discount, SaleProduct.discount_percent
+            FROM SaleProduct
+            JOIN Product ON SaleProduct.product_id = Product.product_id
+            WHERE SaleProduct.sale_id = ?
+            ",
+            sale.sale_id
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        let columns = vec![
+            sale.sale_id.to_string(),
+            sale.date.clone(),
+            sale.status.to_string(),
+            sale.client_name.clone(),
+            sale.rep_name.clone(),
+            money(sale.discount_amount),
+            money(sale.shipping),
+            money(sale.total),
+            money(sale.cost),
+            money(sale.net),
+            money(sale.refunded),
+            money(sale.rep_cut.unwrap_or(0.00)),
+            sale.note.clone().unwrap_or_default(),
+        ];
+
+        if lines.is_empty() {
+            let mut row = columns.clone();
+            row.extend(vec![String::new(); 5]);
+            rows.push(row);
+        }
+
+        for line in &lines {
+            let amount = line.msrp_at_sale * line.qty as f64;
+            let mut row = columns.clone();
+            row.extend([
+                line.name.clone(),
+                line.qty.to_string(),
+                money(line.msrp_at_sale),
+                money(discount_off(amount, line.discount, line.discount_percent)),
+                money(line.cost_at_sale),
+            ]);
+            rows.push(row);
+        }
+    }
+
+    write_csv(
+        &path,
+        &[
+            "Sale",
+            "Date",
+            "Status",
+            "Client",
+            "Rep",
+            "Discount",
+            "Shipping",
+            "Total",
+            "Cost",
+            "Net",
+            "Refunded",
+            "Rep Cut",
+            "Note",
+            "Product",
+            "Qty",
+            "MSRP",
+            "Line Discount",
+            "Cost At Sale",
+        ],
+        rows,
+    )
+    .await
+}
+
+pub async fn export_purchases(
+    pool: SqlitePool,
+    purchases: Vec<Purchase>,
+    path: String,
+) -> Result<(usize, String), Errorr> {
+    let mut rows = Vec::new();
+
+    for purchase in &purchases {
+        let parts = get_purchase_parts(pool.clone(), purchase.id).await?;
+        let columns = vec![
+            purchase.id.to_string(),
+            purchase.date.clone(),
+            money(purchase.total),
+            purchase.note.clone().unwrap_or_default(),
+        ];
+
+        if parts.is_empty() {
+            let mut row = columns.clone();
+            row.extend(vec![String::new(); 3]);
+            rows.push(row);
+        }
+
+        for part in &parts {
+            let mut row = columns.clone();
+            row.extend([part.name.clone(), part.qty.to_string(), money(part.cost)]);
+            rows.push(row);
+        }
+    }
+
+    write_csv(
+        &path,
+        &["Purchase", "Date", "Total", "Note", "Part", "Qty", "Cost"],
+        rows,
+    )
+    .await
+}
+
+pub async fn export_manufactures(
+    pool: SqlitePool,
+    manufactures: Vec<Manufacture>,
+    path: String,
+) -> Result<(usize, String), Errorr> {
+    let mut rows = Vec::new();
+
+    for manufacture in &manufactures {
+        let products = get_manufacture_products(pool.clone(), manufacture.id).await?;
+        let columns = vec![
+            manufacture.id.to_string(),
+            manufacture.date.clone(),
+            manufacture.backorder_reason.clone().unwrap_or_default(),
+        ];
+
+        if products.is_empty() {
+            let mut row = columns.clone();
+            row.extend(vec![String::new(); 2]);
+            rows.push(row);
+        }
+
+        for product in &products {
+            let mut row = columns.clone();
+            row.extend([product.name.clone(), product.qty.to_string()]);
+            rows.push(row);
+        }
+    }
+
+    write_csv(
+        &path,
+        &["Manufacture", "Date", "Backorder Reason", "Product", "Qty"],
+        rows,
+    )
+    .await
+}
+
+pub async fn export_products(
+    pool: SqlitePool,
+    products: Vec<Product>,
+    path: String,
+) -> Result<(usize, String), Errorr> {
+    let mut rows = Vec::new();
+
+    for product in &products {
+        let parts = get_product_parts(pool.clone(), product.product_id).await?;
+        let columns = vec![
+            product.name.clone(),
+            product.units.to_string(),
+            money(product.cost),
+            money(product.msrp),
+            product.reorder_point.to_string(),
+            product
+                .reorder_qty
+                .map(|q| q.to_string())
+                .unwrap_or_default(),
+        ];
+
+        if parts.is_empty() {
+            let mut row = columns.clone();
+            row.extend(vec![String::new(); 3]);
+            rows.push(row);
+        }
+
+        for part in &parts {
+            let mut row = columns.clone();
+            row.extend([part.name.clone(), part.qty.to_string(), money(part.cost)]);
+            rows.push(row);
+        }
+    }
+
+    write_csv(
+        &path,
+        &[
+            "Product",
+            "Units",
+            "Cost",
+            "MSRP",
+            "Reorder Point",
+            "Reorder Qty",
+            "Part",
+            "Part Qty",
+            "Part Cost",
+        ],
+        rows,
+    )
+    .await
+}
+
+pub async fn export_parts(parts: Vec<Part>, path: String) -> Result<(usize, String), Errorr> {
+    let rows = parts
+        .iter()
+        .map(|part| {
+            vec![
+                part.name.clone(),
+                money(part.cost),
+                part.units_left.to_string(),
+                money(part.total_spent),
+                part.total_units_purchased.to_string(),
+                part.reorder_point.to_string(),
+                part.reorder_qty.map(|q| q.to_string()).unwrap_or_default(),
+            ]
+        })
+        .collect();
+
+    write_csv(
+        &path,
+        &[
+            "Part",
+            "Cost",
+            "Units Left",
+            "Total Spent",
+            "Total Purchased",
+            "Reorder Point",
+            "Reorder Qty",
+        ],
+        rows,
+    )
+    .await
+}
+
+pub async fn export_clients(clients: Vec<Client>, path: String) -> Result<(usize, String), Errorr> {
+    let rows = clients
+        .iter()
+        .map(|client| {
+            vec![
+                client.name.clone(),
+                client.email.clone().unwrap_or_default(),
+                client.address.clone(),
+            ]
+        })
+        .collect();
+
+    write_csv(&path, &["Name", "Email", "Address"], rows).await
+}
+
+pub async fn export_reps(reps: Vec<Rep>, path: String) -> Result<(usize, String), Errorr> {
+    let rows = reps
+        .iter()
+        .map(|rep| {
+            vec![
+                rep.name.clone(),
+                rep.percentage.to_string(),
+                money(rep.balance),
+            ]
+        })
+        .collect();
+
+    write_csv(&path, &["Name", "Percentage", "Unpaid"], rows).await
+}
@@ -0,0 +1,595 @@
+use std::fmt;
+
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{error::Errorr, repo::product::recompute_product_costs};
+
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub enum ImportKind {
+    #[default]
+    Clients,
+    Parts,
+    Products,
+}
+
+impl ImportKind {
+    pub const ALL: [ImportKind; 3] = [ImportKind::Clients, ImportKind::Parts, ImportKind::Products];
+
+    // Labels match the export headers so an exported file maps itself.
+    pub fn fields(&self) -> &'static [&'static str] {
+        match self {
+            ImportKind::Clients => &["Name", "Email", "Address"],
+            ImportKind::Parts => &["Part", "Reorder Point", "Reorder Qty"],
+            ImportKind::Products => &[
+                "Product",
+                "MSRP",
+                "Reorder Point",
+                "Reorder Qty",
+                "Part",
+                "Part Qty",
+            ],
+        }
+    }
+}
+
+impl fmt::Display for ImportKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ImportKind::Clients => "Clients",
+            ImportKind::Parts => "Parts",
+            ImportKind::Products => "Products",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct ImportSource {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ImportSource {
+    // Picks the column whose header matches each field, a lone "Name" column
+    // also stands in for the part or product name.
+    pub fn auto_map(&self, kind: ImportKind) -> Vec<Option<usize>> {
+        kind.fields()
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let found = self
+                    .headers
+                    .iter()
+                    .position(|h| h.eq_ignore_ascii_case(field));
+                match found {
+                    None if i == 0 => self
+                        .headers
+                        .iter()
+                        .position(|h| h.eq_ignore_ascii_case("name")),
+                    found => found,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct ImportRow {
+    pub line: usize,
+    pub values: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => quoted = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows
+}
+
+pub async fn read_csv(path: String) -> Result<ImportSource, Errorr> {
+    if path.trim().is_empty() {
+        return Err(Errorr::InvalidInput(String::from(
+            "Enter a file path to import from",
+        )));
+    }
+
+    let text = tokio::fs::read_to_string(path.trim()).await?;
+    let mut rows = parse_csv(text.trim_start_matches('\u{feff}')).into_iter();
+
+    let headers: Vec<String> = match rows.next() {
+        Some(h) => h.iter().map(|s| s.trim().to_string()).collect(),
+        None => return Err(Errorr::InvalidInput(format!("{} is empty", path.trim()))),
+    };
+
+    Ok(ImportSource {
+        headers,
+        rows: rows
+            .filter(|r| r.iter().any(|f| !f.trim().is_empty()))
+            .collect(),
+    })
+}
+
+struct Existing {
+    names: Vec<String>,
+    parts: Vec<(String, i64)>,
+}
+
+async fn get_existing(conn: &mut SqliteConnection, kind: ImportKind) -> Result<Existing, Errorr> {
+    let names = match kind {
+        ImportKind::Clients => {
+            sqlx::query_scalar!("SELECT name FROM Client")
+                .fetch_all(&mut *conn)
+                .await?
+        }
+        ImportKind::Parts => {
+            sqlx::query_scalar!("SELECT name FROM Part")
+                .fetch_all(&mut *conn)
+                .await?
+        }
+        ImportKind::Products => {
+            sqlx::query_scalar!("SELECT name FROM Product")
+                .fetch_all(&mut *conn)
+                .await?
+        }
+    };
+
+    let parts = sqlx::query!("SELECT part_id, name FROM Part")
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|p| (p.name.trim().to_lowercase(), p.part_id))
+        .collect();
+
+    Ok(Existing {
+        names: names.iter().map(|n| n.trim().to_lowercase()).collect(),
+        parts,
+    })
+}
+
+// An empty cell takes the same default as Part and Product.reorder_point.
+fn reorder_point(value: &str) -> Result<i64, String> {
+    if value.is_empty() {
+        return Ok(25);
+    }
+
+    value
+        .parse::<u32>()
+        .map(i64::from)
+        .map_err(|_| format!("Reorder Point \"{}\" is not a whole number", value))
+}
+
+// An empty cell leaves the reorder quantity unset.
+fn reorder_qty(value: &str) -> Result<Option<i64>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    match value.parse::<u32>() {
+        Ok(0) => Err(format!("Reorder Qty \"{}\" must be at least 1", value)),
+        Ok(q) => Ok(Some(i64::from(q))),
+        Err(_) => Err(format!("Reorder Qty \"{}\" is not a whole number", value)),
+    }
+}
+
+fn msrp(value: &str) -> Result<f64, String> {
+    if value.is_empty() {
+        return Err(String::from("MSRP is required"));
+    }
+
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|m| m.is_finite() && *m >= 0.00)
+        .ok_or(format!("MSRP \"{}\" is not a valid amount", value))
+}
+
+fn part_qty(value: &str) -> Result<i64, String> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|q| *q > 0)
+        .map(i64::from)
+        .ok_or(format!("Part Qty \"{}\" must be at least 1", value))
+}
+
+// Names are compared trimmed and case-insensitively. A product spreads over
+// one line per part, so its lines must agree on everything but the part.
+fn validate_rows(
+    kind: ImportKind,
+    source: &ImportSource,
+    mapping: &[Option<usize>],
+    existing: &Existing,
+) -> Vec<ImportRow> {
+    let mut rows: Vec<ImportRow> = Vec::new();
+
+    for (i, record) in source.rows.iter().enumerate() {
+        let values: Vec<String> = mapping
+            .iter()
+            .map(|m| {
+                m.and_then(|c| record.get(c))
+                    .map(|v| v.trim().to_string())
+                    .unwrap_or_default()
+            })
+            .collect();
+        let mut errors = Vec::new();
+        let name = values[0].to_lowercase();
+        let field = kind.fields()[0];
+
+        if name.is_empty() {
+            errors.push(format!("{} is required", field));
+        } else if existing.names.contains(&name) {
+            errors.push(format!("{} \"{}\" already exists", field, values[0]));
+        }
+
+        let earlier = rows
+            .iter()
+            .find(|r| !name.is_empty() && r.values[0].to_lowercase() == name);
+
+        match kind {
+            ImportKind::Clients => {
+                if values[2].is_empty() {
+                    errors.push(String::from("Address is required"));
+                }
+                if let Some(r) = earlier {
+                    errors.push(format!("Duplicate of line {}", r.line));
+                }
+            }
+            ImportKind::Parts => {
+                if let Err(e) = reorder_point(&values[1]) {
+                    errors.push(e);
+                }
+                if let Err(e) = reorder_qty(&values[2]) {
+                    errors.push(e);
+                }
+                if let Some(r) = earlier {
+                    errors.push(format!("Duplicate of line {}", r.line));
+                }
+            }
+            ImportKind::Products => {
+                if let Err(e) = msrp(&values[1]) {
+                    errors.push(e);
+                }
+                if let Err(e) = reorder_point(&values[2]) {
+                    errors.push(e);
+                }
+                if let Err(e) = reorder_qty(&values[3]) {
+                    errors.push(e);
+                }
+
+                let part = values[4].to_lowercase();
+                if part.is_empty() {
+                    if !values[5].is_empty() {
+                        errors.push(String::from("Part Qty given without a Part"));
+                    }
+                } else {
+                    if !existing.parts.iter().any(|(n, _)| *n == part) {
+                        errors.push(format!("Unknown part \"{}\"", values[4]));
+                    }
+                    if let Err(e) = part_qty(&values[5]) {
+                        errors.push(e);
+                    }
+                }
+
+                if let Some(r) = earlier {
+                    if r.values[1..4] != values[1..4] {
+                        errors.push(format!("Product details differ from line {}", r.line));
+                    }
+                }
+                let repeat = rows.iter().find(|r| {
+                    !part.is_empty()
+                        && r.values[0].to_lowercase() == name
+                        && r.values[4].to_lowercase() == part
+                });
+                if let Some(r) = repeat {
+                    errors.push(format!("Part already listed on line {}", r.line));
+                }
+            }
+        }
+
+        rows.push(ImportRow {
+            // Line 1 is the header.
+            line: i + 2,
+            values,
+            errors,
+        });
+    }
+
+    rows
+}
+
+pub async fn preview_import(
+    pool: SqlitePool,
+    kind: ImportKind,
+    source: ImportSource,
+    mapping: Vec<Option<usize>>,
+) -> Result<Vec<ImportRow>, Errorr> {
+    let mut conn = pool.acquire().await?;
+    let existing = get_existing(&mut conn, kind).await?;
+
+    Ok(validate_rows(kind, &source, &mapping, &existing))
+}
+
+// Validation runs again inside the transaction so the preview can't go stale,
+// any error leaves the database untouched.
+pub async fn commit_import(
+    pool: SqlitePool,
+    kind: ImportKind,
+    source: ImportSource,
+    mapping: Vec<Option<usize>>,
+) -> Result<usize, Errorr> {
+    let mut tx = pool.begin().await?;
+    let existing = get_existing(&mut tx, kind).await?;
+    let rows = validate_rows(kind, &source, &mapping, &existing);
+
+    if rows.is_empty() {
+        return Err(Errorr::InvalidInput(String::from(
+            "There are no rows to import",
+        )));
+    }
+
+    let bad = rows.iter().filter(|r| !r.errors.is_empty()).count();
+    if bad > 0 {
+        return Err(Errorr::InvalidInput(format!(
+            "{} of {} rows have errors, nothing was imported",
+            bad,
+            rows.len()
+        )));
+    }
+
+    let mut imported = 0;
+
+    match kind {
+        ImportKind::Clients => {
+            for row in &rows {
+                let email = Some(row.values[1].as_str()).filter(|e| !e.is_empty());
+                sqlx::query!(
+                    "
+                    INSERT INTO Client (name, address, email)
+                    VALUES (?,?,?)
+                    ",
+                    row.values[0],
+                    row.values[2],
+                    email
+                )
+                .execute(&mut *tx)
+                .await?;
+                imported += 1;
+            }
+        }
+        ImportKind::Parts => {
+            for row in &rows {
+                let reorder_point = reorder_point(&row.values[1]).map_err(Errorr::InvalidInput)?;
+                let reorder_qty = reorder_qty(&row.values[2]).map_err(Errorr::InvalidInput)?;
+                sqlx::query!(
+                    "
+                    INSERT INTO Part (name, reorder_point, reorder_qty)
+                    VALUES (?,?,?)
+                    ",
+                    row.values[0],
+                    reorder_point,
+                    reorder_qty
+                )
+                .execute(&mut *tx)
+                .await?;
+                imported += 1;
+            }
+        }
+        ImportKind::Products => {
+            let mut products: Vec<(String, i64)> = Vec::new();
+
+            for row in &rows {
+                let name = row.values[0].to_lowercase();
+                let product_id = match products.iter().find(|(n, _)| *n == name) {
+                    Some((_, id)) => *id,
+                    None => {
+                        let msrp = msrp(&row.values[1]).map_err(Errorr::InvalidInput)?;
+                        let reorder_point =
+                            reorder_point(&row.values[2]).map_err(Errorr::InvalidInput)?;
+                        let reorder_qty =
+                            reorder_qty(&row.values[3]).map_err(Errorr::InvalidInput)?;
+                        let r = sqlx::query!(
+                            "
+                            INSERT INTO Product (name, msrp, reorder_point, reorder_qty)
+                            VALUES (?,?,?,?)
+                            ",
+                            row.values[0],
+                            msrp,
+                            reorder_point,
+                            reorder_qty
+                        )
+                        .execute(&mut *tx)
+                        .await?;
+                        let id = r.last_insert_rowid();
+                        products.push((name, id));
+                        imported += 1;
+                        id
+                    }
+                };
+
+                let part = row.values[4].to_lowercase();
+                if let Some((_, part_id)) = existing.parts.iter().find(|(n, _)| *n == part) {
+                    let qty = part_qty(&row.values[5]).map_err(Errorr::InvalidInput)?;
+                    sqlx::query!(
+                        "
+                        INSERT INTO ProductPart (qty, cost, product_id, part_id)
+                        SELECT ?, cost, ?, part_id FROM Part
+                        WHERE part_id = ?
+                        ",
+                        qty,
+                        product_id,
+                        part_id
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+
+            recompute_product_costs(&mut tx).await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(headers: &[&str], rows: &[&[&str]]) -> ImportSource {
+        ImportSource {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            rows: rows
+                .iter()
+                .map(|r| r.iter().map(|f| f.to_string()).collect())
+                .collect(),
+        }
+    }
+
+    fn existing() -> Existing {
+        Existing {
+            names: vec![String::from("old part")],
+            parts: vec![(String::from("bolt"), 1)],
+        }
+    }
+
+    fn validate(kind: ImportKind, source: &ImportSource) -> Vec<ImportRow> {
+        let mapping = source.auto_map(kind);
+        validate_rows(kind, source, &mapping, &existing())
+    }
+
+    #[test]
+    fn parse_csv_handles_quotes_and_line_endings() {
+        let rows = parse_csv("Name,Note\r\n\"Smith, J\",\"says \"\"hi\"\"\nline\"\nlast,");
+
+        assert_eq!(
+            rows,
+            vec![
+                vec!["Name", "Note"],
+                vec!["Smith, J", "says \"hi\"\nline"],
+                vec!["last", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_rows_reports_bad_reorder_cells() {
+        let source = source(
+            &["Part", "Reorder Point", "Reorder Qty"],
+            &[
+                &["Nut", "ten", ""],
+                &["Washer", "", "-3"],
+                &["Spring", "", ""],
+                &["Clip", "", "0"],
+            ],
+        );
+
+        let rows = validate(ImportKind::Parts, &source);
+
+        assert_eq!(
+            rows[0].errors,
+            vec!["Reorder Point \"ten\" is not a whole number"]
+        );
+        assert_eq!(
+            rows[1].errors,
+            vec!["Reorder Qty \"-3\" is not a whole number"]
+        );
+        assert!(rows[2].errors.is_empty());
+        assert_eq!(reorder_point(&rows[2].values[1]), Ok(25));
+        assert_eq!(reorder_qty(&rows[2].values[2]), Ok(None));
+        assert_eq!(rows[3].errors, vec!["Reorder Qty \"0\" must be at least 1"]);
+    }
+
+    #[test]
+    fn validate_rows_catches_existing_and_repeated_names() {
+        let source = source(
+            &["Name", "Reorder Point"],
+            &[
+                &["Old Part", ""],
+                &["Gear", ""],
+                &[" gear ", ""],
+                &["", "4"],
+            ],
+        );
+
+        let rows = validate(ImportKind::Parts, &source);
+
+        assert_eq!(rows[0].errors, vec!["Part \"Old Part\" already exists"]);
+        assert!(rows[1].errors.is_empty());
+        assert_eq!(rows[2].errors, vec!["Duplicate of line 3"]);
+        assert_eq!(rows[3].errors, vec!["Part is required"]);
+    }
+
+    #[test]
+    fn validate_rows_checks_product_lines_against_each_other() {
+        let source = source(
+            &[
+                "Product",
+                "MSRP",
+                "Reorder Point",
+                "Reorder Qty",
+                "Part",
+                "Part Qty",
+            ],
+            &[
+                &["Lamp", "20", "", "", "Bolt", "2"],
+                &["Lamp", "25", "", "", "Bolt", "1"],
+                &["Desk", "", "", "", "Screw", "0"],
+            ],
+        );
+
+        let rows = validate(ImportKind::Products, &source);
+
+        assert!(rows[0].errors.is_empty());
+        assert_eq!(
+            rows[1].errors,
+            vec![
+                "Product details differ from line 2",
+                "Part already listed on line 2"
+            ]
+        );
+        assert_eq!(
+            rows[2].errors,
+            vec![
+                "MSRP is required",
+                "Unknown part \"Screw\"",
+                "Part Qty \"0\" must be at least 1"
+            ]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::{fmt, path::Path};
+
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    error::Errorr,
+    repo::{
+        client::Client,
+        money,
+        sale::{discount_off, get_sale_products_and_client, Sale, SaleProduct},
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Document {
+    Invoice,
+    PackingSlip,
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Document::Invoice => "Invoice",
+            Document::PackingSlip => "Packing Slip",
+        };
+        f.write_str(s)
+    }
+}
+
+pub fn invoice_label(invoice_number: i64) -> String {
+    format!("INV-{:05}", invoice_number)
+}
+
+// A sale gets its number the first time a document is made for it and keeps
+// it. AUTOINCREMENT means a number is never handed out twice, even after the
+// sale it belonged to is deleted.
+async fn invoice_number(conn: &mut SqliteConnection, sale_id: i64) -> Result<i64, Errorr> {
+    sqlx::query!(
+        "
+        INSERT OR IGNORE INTO Invoice (sale_id, issued)
+        VALUES (?, date('now', 'localtime'))
+        ",
+        sale_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let n = sqlx::query_scalar!(
+        "SELECT invoice_number as `invoice_number!` FROM Invoice WHERE sale_id = ?",
+        sale_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(n)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn address_block(client: &Client) -> String {
+    let mut lines = vec![escape(&client.name)];
+    lines.extend(client.address.lines().map(escape));
+    if let Some(email) = client.email.as_ref().filter(|e| !e.is_empty()) {
+        lines.push(escape(email));
+    }
+
+    lines.join("<br>")
+}
+
+fn render(
+    document: Document,
+    label: &str,
+    sale: &Sale,
+    client: &Client,
+    lines: &[SaleProduct],
+) -> String {
+    let priced = document == Document::Invoice;
+    let mut rows = String::new();
+    let mut subtotal = 0.00;
+
+    for line in lines {
+        // The invoice bills what was sold, returns show up as the refund.
+        // The packing slip only lists what's still with the client.
+        let qty = if priced {
+            line.qty
+        } else {
+            line.qty - line.returned
+        };
+        if qty <= 0 {
+            continue;
+        }
+
+        let amount = line.line_total();
+        subtotal += amount;
+        let name = match (priced, line.discount) {
+            (true, d) if d > 0.00 && line.discount_percent => format!("{} ({}% off)", line.name, d),
+            (true, d) if d > 0.00 => format!("{} ({} off)", line.name, money(d)),
+            _ => line.name.clone(),
+        };
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td class=\"num\">{}</td>",
+            escape(&name),
+            qty
+        ));
+        if priced {
+            rows.push_str(&format!(
+                "<td class=\"num\">{}</td><td class=\"num\">{}</td>",
+                money(line.msrp_at_sale),
+                money(amount)
+            ));
+        }
+        rows.push_str("</tr>\n");
+    }
+
+    let head = if priced {
+        "<th>Product</th><th class=\"num\">Qty</th><th class=\"num\">Unit Price</th><th class=\"num\">Amount</th>"
+    } else {
+        "<th>Product</th><th class=\"num\">Qty</th>"
+    };
+
+    let mut totals = String::new();
+    if priced {
+        totals.push_str(&format!(
+            "<tr><td colspan=\"3\">Subtotal</td><td class=\"num\">{}</td></tr>\n",
+            money(subtotal)
+        ));
+        // Line discounts are already in the amounts, the sale discount comes
+        // off their sum the same way it did when the sale was totalled.
+        let discount = discount_off(
+            subtotal,
+            sale.discount.unwrap_or(0.00),
+            sale.discount_percent,
+        );
+        if discount >= 0.005 {
+            totals.push_str(&format!(
+                "<tr><td colspan=\"3\">Discount</td><td class=\"num\">-{}</td></tr>\n",
+                money(discount)
+            ));
+        }
+        totals.push_str(&format!(
+            "<tr><td colspan=\"3\">Shipping</td><td class=\"num\">{}</td></tr>\n",
+            money(sale.shipping)
+        ));
+        totals.push_str(&format!(
+            "<tr class=\"total\"><td colspan=\"3\">Total</td><td class=\"num\">{}</td></tr>\n",
+            money(sale.total)
+        ));
+        if sale.refunded != 0.00 {
+            totals.push_str(&format!(
+                "<tr><td colspan=\"3\">Refunded</td><td class=\"num\">-{}</td></tr>\n",
+                money(sale.refunded)
+            ));
+        }
+    }
+
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{document} {label}</title>
+<style>
+body {{ font-family: sans-serif; margin: 40px; color: #222; }}
+table {{ width: 100%; border-collapse: collapse; margin-top: 24px; }}
+th, td {{ padding: 6px 8px; border-bottom: 1px solid #ddd; text-align: left; }}
+.num {{ text-align: right; }}
+.total td {{ font-weight: bold; border-top: 2px solid #222; }}
+</style>
+</head>
+<body>
+<h1>{document}</h1>
+<p>{label}<br>Sale #{sale_id}<br>Date: {date}</p>
+<h3>Ship To</h3>
+<p>{address}</p>
+<table>
+<thead><tr>{head}</tr></thead>
+<tbody>
+{rows}{totals}</tbody>
+</table>
+</body>
+</html>
+",
+        document = document,
+        label = label,
+        sale_id = sale.sale_id,
+        date = escape(&sale.date),
+        address = address_block(client),
+        head = head,
+        rows = rows,
+        totals = totals,
+    )
+}
+
+// Writes the document as HTML into the folder and returns the file path, the
+// file name carries the invoice number so reprints overwrite the same file.
+pub async fn write_document(
+    pool: SqlitePool,
+    sale: Sale,
+    document: Document,
+    dir: String,
+) -> Result<String, Errorr> {
+    if dir.trim().is_empty() {
+        return Err(Errorr::InvalidInput(String::from(
+            "Enter a folder to save the document in",
+        )));
+    }
+
+    let mut conn = pool.acquire().await?;
+    let label = invoice_label(invoice_number(&mut conn, sale.sale_id).await?);
+    drop(conn);
+
+    let sc = get_sale_products_and_client(pool, sale.sale_id, sale.client_id).await?;
+    let html = render(document, &label, &sale, &sc.client, &sc.sale_products);
+
+    let file_name = match document {
+        Document::Invoice => format!("{}.html", label),
+        Document::PackingSlip => format!("{}-packing-slip.html", label),
+    };
+    let path = Path::new(dir.trim()).join(file_name);
+    tokio::fs::write(&path, html).await?;
+
+    Ok(path.display().to_string())
+}
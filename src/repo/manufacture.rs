@@ -0,0 +1,800 @@
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    error::Errorr,
+    repo::{
+        product::{get_products, Product},
+        shortage_message, validate_date, Shortage,
+    },
+};
+
+#[derive(Default, Clone, Debug)]
+pub struct Manufacture {
+    pub id: i64,
+    pub date: String,
+    pub backorder_reason: Option<String>,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct ManufactureToAdd {
+    pub date: String,
+    pub qty: i64,
+    pub backorder_reason: Option<String>,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct ProductToSelect {
+    pub product_id: i64,
+    pub name: String,
+    pub qty: i64,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct PartRequirement {
+    pub product_id: i64,
+    pub part_id: i64,
+    pub name: String,
+    pub qty: i64,
+    pub units_left: i64,
+}
+
+#[derive(Clone, Debug)]
+struct PartUsage {
+    id: i64,
+    part_id: i64,
+    product_id: Option<i64>,
+    qty: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProductsParts {
+    pub products: Vec<Product>,
+    pub part_requirements: Vec<PartRequirement>,
+}
+
+// Parts whose stock would go negative if the selected products were built,
+// summed across every product that uses the part.
+pub fn manufacture_shortages(
+    products: &[ProductToSelect],
+    part_requirements: &[PartRequirement],
+) -> Vec<Shortage> {
+    let mut needed: Vec<(i64, Shortage)> = Vec::new();
+
+    for product in products {
+        for req in part_requirements
+            .iter()
+            .filter(|r| r.product_id == product.product_id)
+        {
+            match needed.iter_mut().find(|(id, _)| *id == req.part_id) {
+                Some((_, s)) => s.needed += req.qty * product.qty,
+                None => needed.push((
+                    req.part_id,
+                    Shortage {
+                        name: req.name.clone(),
+                        needed: req.qty * product.qty,
+                        available: req.units_left,
+                    },
+                )),
+            }
+        }
+    }
+
+    needed
+        .into_iter()
+        .map(|(_, s)| s)
+        .filter(|s| s.needed > s.available)
+        .collect()
+}
+
+pub async fn get_manufactures(pool: SqlitePool) -> Result<Vec<Manufacture>, Errorr> {
+    let manufactures = sqlx::query_as!(Manufacture, "SELECT * FROM Manufacture")
+        .fetch_all(&pool)
+        .await?;
+
+    Ok(manufactures)
+}
+
+// Signed per-product difference between two sets of lines. Products dropped
+// from the run come back negative.
+pub fn quantity_changes(old: &[ProductToSelect], new: &[ProductToSelect]) -> Vec<ProductToSelect> {
+    let mut changes: Vec<ProductToSelect> = new.to_vec();
+
+    for product in old {
+        match changes
+            .iter_mut()
+            .find(|c| c.product_id == product.product_id)
+        {
+            Some(c) => c.qty -= product.qty,
+            None => changes.push(ProductToSelect {
+                qty: -product.qty,
+                ..product.clone()
+            }),
+        }
+    }
+
+    changes.retain(|c| c.qty != 0);
+    changes
+}
+
+async fn get_part_requirements(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<PartRequirement>, Errorr> {
+    let part_requirements = sqlx::query_as!(
+        PartRequirement,
+        "
+        SELECT ProductPart.product_id, ProductPart.part_id, ProductPart.qty,
+        Part.name, Part.units_left
+        FROM ProductPart
+        JOIN Part ON Part.part_id = ProductPart.part_id
+        "
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(part_requirements)
+}
+
+// Adds the products to stock and takes their parts from Part.units_left,
+// recording the part usage against the run and product.
+async fn build_products(
+    conn: &mut SqliteConnection,
+    manufacture_id: i64,
+    products: &[ProductToSelect],
+    part_requirements: &[PartRequirement],
+) -> Result<(), Errorr> {
+    for product in products {
+        sqlx::query!(
+            "
+            UPDATE Product
+            SET units = units + ?
+            WHERE product_id = ?
+            ",
+            product.qty,
+            product.product_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        for req in part_requirements
+            .iter()
+            .filter(|r| r.product_id == product.product_id)
+        {
+            let used = req.qty * product.qty;
+
+            sqlx::query!(
+                "
+                UPDATE Part
+                SET units_left = units_left - ?
+                WHERE part_id = ?
+                ",
+                used,
+                req.part_id
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            let r = sqlx::query!(
+                "
+                UPDATE ManufacturePart
+                SET qty = qty + ?
+                WHERE manufacture_id = ? AND part_id = ? AND product_id = ?
+                ",
+                used,
+                manufacture_id,
+                req.part_id,
+                product.product_id
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            if r.rows_affected() == 0 {
+                sqlx::query!(
+                    "
+                    INSERT INTO ManufacturePart (qty, part_id, manufacture_id, product_id)
+                    VALUES (?,?,?,?)
+                    ",
+                    used,
+                    req.part_id,
+                    manufacture_id,
+                    product.product_id
+                )
+                .execute(&mut *conn)
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn get_part_usage(
+    conn: &mut SqliteConnection,
+    manufacture_id: i64,
+) -> Result<Vec<PartUsage>, Errorr> {
+    let usage = sqlx::query_as!(
+        PartUsage,
+        "
+        SELECT id, part_id, product_id, qty FROM ManufacturePart
+        WHERE manufacture_id = ?
+        ",
+        manufacture_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(usage)
+}
+
+// Usage recorded before it was kept per product is split between the run's
+// products by what the current bill of materials says each of them needs of
+// the part. Runs with no usage recorded at all get it from the bill of
+// materials outright.
+async fn attribute_usage(
+    conn: &mut SqliteConnection,
+    manufacture_id: i64,
+    products: &[ProductToSelect],
+    part_requirements: &[PartRequirement],
+) -> Result<Vec<PartUsage>, Errorr> {
+    let usage = get_part_usage(conn, manufacture_id).await?;
+
+    if usage.is_empty() {
+        build_usage(conn, manufacture_id, products, part_requirements).await?;
+        return get_part_usage(conn, manufacture_id).await;
+    }
+
+    for row in usage.iter().filter(|u| u.product_id.is_none()) {
+        let shares: Vec<(i64, i64)> = products
+            .iter()
+            .filter_map(|p| {
+                part_requirements
+                    .iter()
+                    .find(|r| r.product_id == p.product_id && r.part_id == row.part_id)
+                    .map(|r| (p.product_id, r.qty * p.qty))
+            })
+            .filter(|(_, w)| *w > 0)
+            .collect();
+        let weight: i64 = shares.iter().map(|(_, w)| w).sum();
+        if weight == 0 {
+            continue;
+        }
+
+        let mut left = row.qty;
+        for (i, (product_id, w)) in shares.iter().enumerate() {
+            let qty = if i == shares.len() - 1 {
+                left
+            } else {
+                row.qty * w / weight
+            };
+            left -= qty;
+
+            sqlx::query!(
+                "
+                INSERT INTO ManufacturePart (qty, part_id, manufacture_id, product_id)
+                VALUES (?,?,?,?)
+                ",
+                qty,
+                row.part_id,
+                manufacture_id,
+                product_id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        sqlx::query!("DELETE FROM ManufacturePart WHERE id = ?", row.id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    get_part_usage(conn, manufacture_id).await
+}
+
+// Records what building the products took without touching stock, for runs
+// whose stock moves already happened.
+async fn build_usage(
+    conn: &mut SqliteConnection,
+    manufacture_id: i64,
+    products: &[ProductToSelect],
+    part_requirements: &[PartRequirement],
+) -> Result<(), Errorr> {
+    for product in products {
+        for req in part_requirements
+            .iter()
+            .filter(|r| r.product_id == product.product_id)
+        {
+            let used = req.qty * product.qty;
+
+            sqlx::query!(
+                "
+                INSERT INTO ManufacturePart (qty, part_id, manufacture_id, product_id)
+                VALUES (?,?,?,?)
+                ",
+                used,
+                req.part_id,
+                manufacture_id,
+                product.product_id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Built units can only be taken back while they're still in stock, units
+// already sold would leave Product.units negative. Reductions are negative.
+async fn check_built_stock(
+    conn: &mut SqliteConnection,
+    reductions: &[ProductToSelect],
+) -> Result<(), Errorr> {
+    let mut shortages = Vec::new();
+    for product in reductions {
+        let units = sqlx::query_scalar!(
+            "SELECT units FROM Product WHERE product_id = ?",
+            product.product_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        if units < -product.qty {
+            shortages.push(Shortage {
+                name: product.name.clone(),
+                needed: -product.qty,
+                available: units,
+            });
+        }
+    }
+    if !shortages.is_empty() {
+        return Err(Errorr::InsufficientStock(shortage_message(&shortages)));
+    }
+
+    Ok(())
+}
+
+// Takes built units back off Product.units and returns the parts they used,
+// going by the usage recorded for the run rather than the bill of materials
+// as it is now. The units must still be in stock.
+async fn unbuild_products(
+    conn: &mut SqliteConnection,
+    reductions: &[ProductToSelect],
+    old_products: &[ProductToSelect],
+    usage: &[PartUsage],
+) -> Result<(), Errorr> {
+    check_built_stock(&mut *conn, reductions).await?;
+
+    for product in reductions {
+        let built = old_products
+            .iter()
+            .find(|p| p.product_id == product.product_id)
+            .map(|p| p.qty)
+            .unwrap_or(0);
+
+        sqlx::query!(
+            "
+            UPDATE Product
+            SET units = units + ?
+            WHERE product_id = ?
+            ",
+            product.qty,
+            product.product_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        for row in usage
+            .iter()
+            .filter(|u| u.product_id == Some(product.product_id))
+        {
+            let returned = returned_parts(row.qty, built, -product.qty);
+
+            sqlx::query!(
+                "
+                UPDATE Part
+                SET units_left = units_left + ?
+                WHERE part_id = ?
+                ",
+                returned,
+                row.part_id
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            if returned == row.qty {
+                sqlx::query!("DELETE FROM ManufacturePart WHERE id = ?", row.id)
+                    .execute(&mut *conn)
+                    .await?;
+            } else {
+                sqlx::query!(
+                    "
+                    UPDATE ManufacturePart
+                    SET qty = qty - ?
+                    WHERE id = ?
+                    ",
+                    returned,
+                    row.id
+                )
+                .execute(&mut *conn)
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Parts given back when `removed` of `built` units are taken off a run that
+// used `used` of the part for them. Removing every unit gives back all of it.
+pub fn returned_parts(used: i64, built: i64, removed: i64) -> i64 {
+    if built <= 0 || removed >= built {
+        used
+    } else {
+        used * removed / built
+    }
+}
+
+async fn insert_lines(
+    conn: &mut SqliteConnection,
+    manufacture_id: i64,
+    products: &[ProductToSelect],
+) -> Result<(), Errorr> {
+    for product in products {
+        sqlx::query!(
+            "
+            INSERT INTO ManufactureProduct (qty, manufacture_id, product_id)
+            VALUES (?,?,?)
+            ",
+            product.qty,
+            manufacture_id,
+            product.product_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn get_manufacture_products(
+    pool: SqlitePool,
+    id: i64,
+) -> Result<Vec<ProductToSelect>, Errorr> {
+    let products = sqlx::query_as!(
+        ProductToSelect,
+        "
+        SELECT ManufactureProduct.product_id, Product.name, ManufactureProduct.qty
+        FROM ManufactureProduct
+        JOIN Product ON Product.product_id = ManufactureProduct.product_id
+        WHERE ManufactureProduct.manufacture_id = ?
+        ",
+        id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(products)
+}
+
+pub async fn get_products_and_parts(pool: SqlitePool) -> Result<ProductsParts, Errorr> {
+    let products = get_products(pool.clone()).await?;
+
+    let mut conn = pool.acquire().await?;
+    let part_requirements = get_part_requirements(&mut conn).await?;
+
+    let r = ProductsParts {
+        products,
+        part_requirements,
+    };
+
+    Ok(r)
+}
+
+pub async fn add_manufacture(
+    pool: SqlitePool,
+    products_to_add: Vec<ProductToSelect>,
+    manufacture_to_add: ManufactureToAdd,
+) -> Result<i64, Errorr> {
+    validate_date(&manufacture_to_add.date)?;
+
+    let date = manufacture_to_add.date;
+    let backorder_reason = manufacture_to_add.backorder_reason;
+
+    let mut tx = pool.begin().await?;
+
+    let part_requirements = get_part_requirements(&mut tx).await?;
+
+    if backorder_reason.is_none() {
+        let shortages = manufacture_shortages(&products_to_add, &part_requirements);
+        if !shortages.is_empty() {
+            return Err(Errorr::InsufficientStock(shortage_message(&shortages)));
+        }
+    }
+
+    let r = sqlx::query!(
+        "
+        INSERT INTO Manufacture (date, backorder_reason)
+        VALUES (?,?)
+        ",
+        date,
+        backorder_reason
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let manufacture_id = r.last_insert_rowid();
+
+    insert_lines(&mut tx, manufacture_id, &products_to_add).await?;
+    build_products(
+        &mut tx,
+        manufacture_id,
+        &products_to_add,
+        &part_requirements,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(manufacture_id)
+}
+
+// Lowered quantities go back by the part usage recorded for the run, raised
+// ones are built with the current bill of materials. Reductions happen first so
+// parts they free up count towards the increases. Dates from before validation
+// only have to be valid once they're edited.
+pub async fn edit_manufacture(
+    pool: SqlitePool,
+    manufacture: Manufacture,
+    products: Vec<ProductToSelect>,
+) -> Result<(), Errorr> {
+    let id = manufacture.id;
+    let date = manufacture.date;
+    let backorder_reason = manufacture.backorder_reason;
+
+    let mut tx = pool.begin().await?;
+
+    let stored_date = sqlx::query_scalar!(
+        "
+        SELECT date FROM Manufacture
+        WHERE id = ?
+        ",
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if date != stored_date {
+        validate_date(&date)?;
+    }
+
+    let old_products = sqlx::query_as!(
+        ProductToSelect,
+        "
+        SELECT ManufactureProduct.product_id, Product.name, ManufactureProduct.qty
+        FROM ManufactureProduct
+        JOIN Product ON Product.product_id = ManufactureProduct.product_id
+        WHERE ManufactureProduct.manufacture_id = ?
+        ",
+        id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let changes = quantity_changes(&old_products, &products);
+    let (increases, reductions): (Vec<ProductToSelect>, Vec<ProductToSelect>) =
+        changes.into_iter().partition(|c| c.qty > 0);
+
+    let part_requirements = get_part_requirements(&mut tx).await?;
+    let usage = attribute_usage(&mut tx, id, &old_products, &part_requirements).await?;
+    unbuild_products(&mut tx, &reductions, &old_products, &usage).await?;
+
+    let part_requirements = get_part_requirements(&mut tx).await?;
+    if backorder_reason.is_none() {
+        let shortages = manufacture_shortages(&increases, &part_requirements);
+        if !shortages.is_empty() {
+            return Err(Errorr::InsufficientStock(shortage_message(&shortages)));
+        }
+    }
+
+    build_products(&mut tx, id, &increases, &part_requirements).await?;
+
+    sqlx::query!(
+        "
+        DELETE FROM ManufactureProduct
+        WHERE manufacture_id = ?
+        ",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let products: Vec<ProductToSelect> = products.into_iter().filter(|p| p.qty > 0).collect();
+    insert_lines(&mut tx, id, &products).await?;
+
+    sqlx::query!(
+        "
+        UPDATE Manufacture
+        SET date = ?, backorder_reason = ?
+        WHERE id = ?
+        ",
+        date,
+        backorder_reason,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn delete_manufacture(pool: SqlitePool, manufacture: Manufacture) -> Result<(), Errorr> {
+    let id = manufacture.id;
+
+    let mut tx = pool.begin().await?;
+
+    let products = sqlx::query!(
+        "
+        SELECT ManufactureProduct.product_id, ManufactureProduct.qty, Product.name
+        FROM ManufactureProduct
+        JOIN Product ON Product.product_id = ManufactureProduct.product_id
+        WHERE manufacture_id = ?
+        ",
+        id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let reductions: Vec<ProductToSelect> = products
+        .iter()
+        .map(|p| ProductToSelect {
+            product_id: p.product_id,
+            name: p.name.clone(),
+            qty: -p.qty,
+        })
+        .collect();
+    check_built_stock(&mut tx, &reductions).await?;
+
+    for product in &products {
+        sqlx::query!(
+            "
+            UPDATE Product
+            SET units = units - ?
+            WHERE product_id = ?
+            ",
+            product.qty,
+            product.product_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let mut parts_used: Vec<(i64, i64)> = sqlx::query!(
+        "
+        SELECT part_id, qty FROM ManufacturePart
+        WHERE manufacture_id = ?
+        ",
+        id
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .iter()
+    .map(|p| (p.part_id, p.qty))
+    .collect();
+
+    // Runs entered before part usage was recorded fall back to the current
+    // bill of materials.
+    if parts_used.is_empty() {
+        parts_used = sqlx::query!(
+            "
+            SELECT ProductPart.part_id AS `part_id!`, ProductPart.qty * ManufactureProduct.qty AS `qty!: i64`
+            FROM ProductPart
+            JOIN ManufactureProduct ON ManufactureProduct.product_id = ProductPart.product_id
+            WHERE ManufactureProduct.manufacture_id = ?
+            ",
+            id
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(|p| (p.part_id, p.qty))
+        .collect();
+    }
+
+    for (part_id, qty) in &parts_used {
+        sqlx::query!(
+            "
+            UPDATE Part
+            SET units_left = units_left + ?
+            WHERE part_id = ?
+            ",
+            qty,
+            part_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query!(
+        "
+        DELETE FROM Manufacture
+        WHERE id = ?
+        ",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product(product_id: i64, qty: i64) -> ProductToSelect {
+        ProductToSelect {
+            product_id,
+            name: format!("Product {}", product_id),
+            qty,
+        }
+    }
+
+    fn requirement(product_id: i64, part_id: i64, qty: i64, units_left: i64) -> PartRequirement {
+        PartRequirement {
+            product_id,
+            part_id,
+            name: format!("Part {}", part_id),
+            qty,
+            units_left,
+        }
+    }
+
+    #[test]
+    fn quantity_changes_are_signed_per_product() {
+        let old = vec![product(1, 5), product(2, 3), product(3, 2)];
+        let new = vec![product(1, 8), product(2, 3), product(4, 1)];
+
+        let changes: Vec<(i64, i64)> = quantity_changes(&old, &new)
+            .iter()
+            .map(|c| (c.product_id, c.qty))
+            .collect();
+
+        assert_eq!(changes, vec![(1, 3), (4, 1), (3, -2)]);
+    }
+
+    #[test]
+    fn manufacture_shortages_sum_a_part_across_products() {
+        let requirements = vec![
+            requirement(1, 10, 2, 10),
+            requirement(2, 10, 1, 10),
+            requirement(2, 20, 1, 50),
+        ];
+
+        let shortages = manufacture_shortages(&[product(1, 4), product(2, 3)], &requirements);
+
+        assert_eq!(
+            shortages,
+            vec![Shortage {
+                name: String::from("Part 10"),
+                needed: 11,
+                available: 10,
+            }]
+        );
+        assert!(manufacture_shortages(&[product(1, 5)], &requirements).is_empty());
+    }
+
+    #[test]
+    fn returned_parts_scale_with_the_units_removed() {
+        assert_eq!(returned_parts(12, 4, 1), 3);
+        assert_eq!(returned_parts(10, 4, 2), 5);
+    }
+
+    #[test]
+    fn returned_parts_give_everything_back_when_the_run_is_emptied() {
+        assert_eq!(returned_parts(7, 3, 3), 7);
+        assert_eq!(returned_parts(7, 3, 5), 7);
+        assert_eq!(returned_parts(7, 0, 1), 7);
+    }
+}
@@ -0,0 +1,75 @@
+use std::str::FromStr;
+
+use regex::Regex;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
+};
+
+use crate::error::Errorr;
+
+pub mod client;
+pub mod export;
+pub mod import;
+pub mod invoice;
+pub mod manufacture;
+pub mod part;
+pub mod payment;
+pub mod product;
+pub mod purchase;
+pub mod rep;
+pub mod report;
+pub mod returns;
+pub mod sale;
+pub mod setting;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shortage {
+    pub name: String,
+    pub needed: i64,
+    pub available: i64,
+}
+
+pub fn shortage_message(shortages: &[Shortage]) -> String {
+    let items: Vec<String> = shortages
+        .iter()
+        .map(|s| format!("{} (need {}, have {})", s.name, s.needed, s.available))
+        .collect();
+
+    format!("Not enough stock: {}", items.join(", "))
+}
+
+pub fn money(v: f64) -> String {
+    format!("${:.2}", v)
+}
+
+// Dates are stored as YYYY-MM-DD text so SQLite's date functions can range
+// and group on them.
+pub fn validate_date(date: &str) -> Result<(), Errorr> {
+    let date_format = Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap();
+    if date_format.is_match(date) {
+        Ok(())
+    } else {
+        Err(Errorr::InvalidInput(format!(
+            "\"{}\" is not a valid date, use YYYY-MM-DD",
+            date
+        )))
+    }
+}
+
+// The pool is created once at startup and cloned into every query, cloning
+// only bumps a reference count.
+pub fn connect(url: &str) -> Result<SqlitePool, Errorr> {
+    let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+    let pool = SqlitePoolOptions::new().connect_lazy_with(options);
+
+    Ok(pool)
+}
+
+// Migrations are embedded at compile time so a fresh or outdated database
+// file is brought up to the current schema on launch.
+pub async fn migrate(pool: SqlitePool) -> Result<(), Errorr> {
+    sqlx::migrate!().run(&pool).await?;
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use sqlx::SqlitePool;
+
+use crate::error::Errorr;
+
+#[derive(Default, Clone, Debug)]
+pub struct Part {
+    pub part_id: i64,
+    pub name: String,
+    pub units_left: i64,
+    pub cost: f64,
+    pub total_spent: f64,
+    pub total_units_purchased: i64,
+    pub reorder_point: i64,
+    pub reorder_qty: Option<i64>,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct PartToAdd {
+    pub name: String,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct PartToSelect {
+    pub part_id: i64,
+    pub name: String,
+    pub cost: String,
+    pub qty: i64,
+    pub total_spent: f64,
+    pub total_units_purchased: i64,
+}
+
+pub async fn get_parts(pool: SqlitePool) -> Result<Vec<Part>, Errorr> {
+    let parts = sqlx::query_as!(Part, "SELECT * FROM Part")
+        .fetch_all(&pool)
+        .await?;
+
+    Ok(parts)
+}
+
+pub async fn get_low_parts(pool: SqlitePool) -> Result<Vec<Part>, Errorr> {
+    let parts = sqlx::query_as!(
+        Part,
+        "
+        SELECT * FROM Part
+        WHERE units_left <= reorder_point
+        "
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(parts)
+}
+
+pub async fn add_part(pool: SqlitePool, part: PartToAdd) -> Result<(), Errorr> {
+    let name = part.name;
+
+    sqlx::query!(
+        "
+        INSERT INTO Part (name)
+        VALUES (?)
+        ",
+        name,
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn edit_part(pool: SqlitePool, part: Part) -> Result<(), Errorr> {
+    let id = part.part_id;
+    let name = part.name.as_str();
+    let reorder_point = part.reorder_point;
+    let reorder_qty = part.reorder_qty;
+
+    sqlx::query!(
+        "
+        UPDATE Part
+        SET name = ?, reorder_point = ?, reorder_qty = ?
+        WHERE part_id = ?
+        ",
+        name,
+        reorder_point,
+        reorder_qty,
+        id
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_part(pool: SqlitePool, part: Part) -> Result<(), Errorr> {
+    let id = part.part_id;
+
+    sqlx::query!(
+        "
+        DELETE FROM Part
+        WHERE part_id = ?
+        ",
+        id
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
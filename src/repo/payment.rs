@@ -0,0 +1,179 @@
+use sqlx::SqlitePool;
+
+use crate::{
+    error::Errorr,
+    repo::{
+        money,
+        sale::{record_status, sale_status, SaleStatus},
+        validate_date,
+    },
+};
+
+#[derive(Default, Clone, Debug)]
+pub struct Payment {
+    pub id: i64,
+    pub sale_id: i64,
+    pub date: String,
+    pub amount: f64,
+    pub method: String,
+    pub reference: Option<String>,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct PaymentToAdd {
+    pub date: String,
+    pub amount: String,
+    pub method: String,
+    pub reference: String,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct Receivable {
+    pub sale_id: i64,
+    pub date: String,
+    pub due_date: Option<String>,
+    pub client_id: i64,
+    pub client_name: String,
+    pub total: f64,
+    pub paid: f64,
+    pub overdue: bool,
+}
+
+impl Receivable {
+    pub fn balance(&self) -> f64 {
+        self.total - self.paid
+    }
+}
+
+pub async fn get_sale_payments(pool: SqlitePool, sale_id: i64) -> Result<Vec<Payment>, Errorr> {
+    let payments = sqlx::query_as!(
+        Payment,
+        "
+        SELECT * FROM Payment
+        WHERE sale_id = ?
+        ORDER BY date, id
+        ",
+        sale_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(payments)
+}
+
+// A payment can't take the balance below zero. Once the sale is paid in full
+// it moves to Paid if its status allows it, through the same history as a
+// manual change. Returns the amount recorded and whether the sale was paid off.
+pub async fn add_payment(
+    pool: SqlitePool,
+    sale_id: i64,
+    payment: PaymentToAdd,
+) -> Result<(f64, bool), Errorr> {
+    validate_date(&payment.date)?;
+
+    let amount = payment
+        .amount
+        .parse::<f64>()
+        .ok()
+        .filter(|a| *a > 0.00)
+        .ok_or(Errorr::InvalidInput(format!(
+            "\"{}\" is not a valid payment amount",
+            payment.amount
+        )))?;
+    let method = payment.method.trim();
+    if method.is_empty() {
+        return Err(Errorr::InvalidInput(String::from(
+            "Enter how the payment was made",
+        )));
+    }
+    let reference = Some(payment.reference.trim()).filter(|r| !r.is_empty());
+    let date = payment.date;
+
+    let mut tx = pool.begin().await?;
+
+    let status = sale_status(&mut tx, sale_id).await?;
+    if status == SaleStatus::Cancelled {
+        return Err(Errorr::InvalidInput(format!(
+            "Sale #{} is {}, it can't take payments",
+            sale_id, status
+        )));
+    }
+
+    let balance = sqlx::query_scalar!(
+        "
+        SELECT total
+        - IFNULL((SELECT SUM(amount) FROM Payment WHERE sale_id = Sale.sale_id), 0.0)
+        AS `balance!: f64`
+        FROM Sale
+        WHERE sale_id = ?
+        ",
+        sale_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Half a cent of slack so rounding in the stored totals can't block the
+    // last payment.
+    if amount > balance + 0.005 {
+        return Err(Errorr::InvalidInput(format!(
+            "{} is more than the {} outstanding on sale #{}",
+            money(amount),
+            money(balance),
+            sale_id
+        )));
+    }
+
+    sqlx::query!(
+        "
+        INSERT INTO Payment (sale_id, date, amount, method, reference)
+        VALUES (?,?,?,?,?)
+        ",
+        sale_id,
+        date,
+        amount,
+        method,
+        reference
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let paid_in_full = balance - amount < 0.005 && status.next().contains(&SaleStatus::Paid);
+    if paid_in_full {
+        record_status(&mut tx, sale_id, Some(status), SaleStatus::Paid).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok((amount, paid_in_full))
+}
+
+// Confirmed sales onwards with money still owed. Refunds are paid back out of
+// what the client paid and don't change what they owe. A sale is due the
+// number of days after its date given by the payment terms it was created under.
+pub async fn get_receivables(pool: SqlitePool) -> Result<Vec<Receivable>, Errorr> {
+    let receivables = sqlx::query_as!(
+        Receivable,
+        "
+        SELECT Sale.sale_id AS `sale_id!: i64`, Sale.date AS `date!: String`,
+        Client.client_id AS `client_id!: i64`, Client.name AS `client_name!: String`,
+        Sale.total AS `total!: f64`,
+        IFNULL((SELECT SUM(amount) FROM Payment WHERE Payment.sale_id = Sale.sale_id), 0.0)
+        AS `paid!: f64`,
+        date(Sale.date, '+' || IFNULL(Setting.payment_terms, 30) || ' days') AS `due_date: String`,
+        IFNULL(date(Sale.date, '+' || IFNULL(Setting.payment_terms, 30) || ' days')
+        < date('now', 'localtime'), 0) AS `overdue!: bool`
+        FROM Sale
+        JOIN Client ON Sale.client_id = Client.client_id
+        LEFT JOIN Setting ON Sale.setting_id = Setting.id
+        WHERE Sale.status NOT IN ('DRAFT', 'CANCELLED')
+        ORDER BY Client.name, Sale.date
+        "
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(receivables
+        .into_iter()
+        .filter(|r| r.balance() >= 0.005)
+        .collect())
+}
@@ -0,0 +1,243 @@
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{error::Errorr, repo::part::PartToSelect};
+
+#[derive(Debug, Default, Clone)]
+pub struct Product {
+    pub product_id: i64,
+    pub name: String,
+    pub units: i64,
+    pub cost: f64,
+    pub msrp: f64,
+    pub reorder_point: i64,
+    pub reorder_qty: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ProductToAdd {
+    pub name: String,
+    pub msrp: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ProductPart {
+    pub id: i64,
+    pub name: String,
+    pub qty: i64,
+    pub cost: f64,
+    pub part_id: i64,
+    pub product_id: i64,
+}
+
+pub async fn get_products(pool: SqlitePool) -> Result<Vec<Product>, Errorr> {
+    let products = sqlx::query_as!(Product, "SELECT * FROM Product")
+        .fetch_all(&pool)
+        .await?;
+
+    Ok(products)
+}
+
+pub async fn get_low_products(pool: SqlitePool) -> Result<Vec<Product>, Errorr> {
+    let products = sqlx::query_as!(
+        Product,
+        "
+        SELECT * FROM Product
+        WHERE units <= reorder_point
+        "
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(products)
+}
+
+pub async fn get_product_parts(
+    pool: SqlitePool,
+    product_id: i64,
+) -> Result<Vec<ProductPart>, Errorr> {
+    let products = sqlx::query_as!(
+        ProductPart,
+        "SELECT ProductPart.id, ProductPart.qty, ProductPart.part_id, ProductPart.product_id,
+                                   Part.name as name, Part.cost as cost
+                                   FROM ProductPart
+                                   JOIN Part ON ProductPart.part_id = Part.part_id
+                                   WHERE ProductPart.product_id = ?
+                                   ",
+        product_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(products)
+}
+
+// Part costs come from Part.cost, the same as an edit, so a new product's cost
+// is right from the start.
+pub async fn add_product(
+    pool: SqlitePool,
+    product: ProductToAdd,
+    parts_to_add: Vec<PartToSelect>,
+) -> Result<(), Errorr> {
+    let name = product.name;
+    let msrp = product
+        .msrp
+        .parse::<f64>()
+        .map_err(|_| Errorr::InvalidInput(format!("\"{}\" is not a valid MSRP", product.msrp)))?;
+
+    let mut tx = pool.begin().await?;
+
+    let r = sqlx::query!(
+        "
+        INSERT INTO Product (name, msrp, cost)
+        VALUES (?,?,0.00)
+        ",
+        name,
+        msrp
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let product_id = r.last_insert_rowid();
+
+    for part in parts_to_add.iter().filter(|p| p.qty > 0) {
+        sqlx::query!(
+            "
+            INSERT INTO ProductPart (qty, cost, product_id, part_id)
+            SELECT ?, cost, ?, part_id FROM Part
+            WHERE part_id = ?
+            ",
+            part.qty,
+            product_id,
+            part.part_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    recompute_product_costs(&mut tx).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn edit_product(
+    pool: SqlitePool,
+    product: Product,
+    parts: Vec<PartToSelect>,
+) -> Result<(), Errorr> {
+    let id = product.product_id;
+    let name = product.name.as_str();
+    let units = product.units;
+    let msrp = product.msrp;
+    let reorder_point = product.reorder_point;
+    let reorder_qty = product.reorder_qty;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "
+        UPDATE Product
+        SET name = ?, units = ?, msrp = ?, reorder_point = ?, reorder_qty = ?
+        WHERE product_id = ?
+        ",
+        name,
+        units,
+        msrp,
+        reorder_point,
+        reorder_qty,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+        DELETE FROM ProductPart
+        WHERE product_id = ?
+        ",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    for part in parts.iter().filter(|p| p.qty > 0) {
+        sqlx::query!(
+            "
+            INSERT INTO ProductPart (qty, cost, product_id, part_id)
+            SELECT ?, cost, ?, part_id FROM Part
+            WHERE part_id = ?
+            ",
+            part.qty,
+            id,
+            part.part_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    recompute_product_costs(&mut tx).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn delete_product(pool: SqlitePool, product: Product) -> Result<(), Errorr> {
+    let id = product.product_id;
+
+    sqlx::query!(
+        "
+        DELETE FROM Product
+        WHERE product_id = ?
+        ",
+        id
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+// Product.cost is the sum of its parts, so it has to follow every change to
+// ProductPart.cost.
+pub async fn recompute_product_costs(conn: &mut SqliteConnection) -> Result<(), Errorr> {
+    let products = sqlx::query!(
+        "
+        SELECT * FROM Product
+        "
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for product in &products {
+        let product_parts = sqlx::query!(
+            "
+            SELECT * FROM ProductPart
+            WHERE product_id = ?
+            ",
+            product.product_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut cost = 0.00;
+        for pp in &product_parts {
+            cost += pp.cost * pp.qty as f64;
+        }
+
+        sqlx::query!(
+            "
+            UPDATE Product
+            SET cost = ?
+            WHERE product_id = ?
+            ",
+            cost,
+            product.product_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
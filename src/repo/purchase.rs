@@ -0,0 +1,342 @@
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    error::Errorr,
+    repo::{part::PartToSelect, product::recompute_product_costs},
+};
+
+#[derive(Default, Clone, Debug)]
+pub struct Purchase {
+    pub id: i64,
+    pub date: String,
+    pub total: f64,
+    pub note: Option<String>,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct PurchasePart {
+    pub id: i64,
+    pub part_id: i64,
+    pub name: String,
+    pub qty: i64,
+    pub cost: f64,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct PurchaseToAdd {
+    pub date: String,
+    pub total: f64,
+    pub note: Option<String>,
+}
+
+pub async fn get_purchases(pool: SqlitePool) -> Result<Vec<Purchase>, Errorr> {
+    let purchases = sqlx::query_as!(Purchase, "SELECT * FROM Purchase")
+        .fetch_all(&pool)
+        .await?;
+
+    Ok(purchases)
+}
+
+pub async fn get_purchase_parts(
+    pool: SqlitePool,
+    purchase_id: i64,
+) -> Result<Vec<PurchasePart>, Errorr> {
+    let purchases = sqlx::query_as!(
+        PurchasePart,
+        "SELECT PurchasePart.qty, PurchasePart.cost, PurchasePart.id, PurchasePart.part_id,
+                                   Part.name as name
+                                   FROM PurchasePart
+                                   JOIN Part ON PurchasePart.part_id = Part.part_id
+                                   WHERE PurchasePart.purchase_id = ?
+                                   ",
+        purchase_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(purchases)
+}
+
+// Rebuilds a part's totals and average cost from the PurchasePart rows that
+// remain, then pushes the new cost into every BOM that uses it.
+pub async fn recompute_part_cost(conn: &mut SqliteConnection, part_id: i64) -> Result<(), Errorr> {
+    let totals = sqlx::query!(
+        "
+        SELECT COALESCE(SUM(qty), 0) AS `units!: i64`, COALESCE(SUM(cost), 0.0) AS `spent!: f64`
+        FROM PurchasePart
+        WHERE part_id = ?
+        ",
+        part_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let cost = if totals.units > 0 {
+        totals.spent / totals.units as f64
+    } else {
+        0.00
+    };
+
+    sqlx::query!(
+        "
+        UPDATE Part
+        SET total_units_purchased = ?, total_spent = ?, cost = ?
+        WHERE part_id = ?
+        ",
+        totals.units,
+        totals.spent,
+        cost,
+        part_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "
+        UPDATE ProductPart
+        SET cost = ?
+        WHERE part_id = ?
+        ",
+        cost,
+        part_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+fn part_cost(part: &PartToSelect) -> Result<f64, Errorr> {
+    part.cost
+        .parse::<f64>()
+        .ok()
+        .filter(|c| c.is_finite() && *c >= 0.00)
+        .ok_or(Errorr::InvalidInput(format!(
+            "\"{}\" is not a valid cost for {}",
+            part.cost, part.name
+        )))
+}
+
+pub async fn add_purchase(
+    pool: SqlitePool,
+    parts_to_add: Vec<PartToSelect>,
+    purchase_to_add: PurchaseToAdd,
+) -> Result<i64, Errorr> {
+    let date = purchase_to_add.date;
+    let total = purchase_to_add.total;
+    let note = purchase_to_add.note;
+
+    let mut tx = pool.begin().await?;
+
+    let r = sqlx::query!(
+        "
+        INSERT INTO Purchase (total, date, note)
+        VALUES (?,?,?)
+        ",
+        total,
+        date,
+        note
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let purchase_id = r.last_insert_rowid();
+
+    for part in &parts_to_add {
+        let cost = part_cost(part)?;
+
+        sqlx::query!(
+            "
+            INSERT INTO PurchasePart (qty, cost, purchase_id, part_id)
+            VALUES (?,?,?,?)
+            ",
+            part.qty,
+            cost,
+            purchase_id,
+            part.part_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "
+            UPDATE Part
+            SET units_left = units_left + ?
+            WHERE part_id = ?
+            ",
+            part.qty,
+            part.part_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        recompute_part_cost(&mut tx, part.part_id).await?;
+    }
+
+    recompute_product_costs(&mut tx).await?;
+
+    tx.commit().await?;
+
+    Ok(purchase_id)
+}
+
+// Swaps the purchase's lines for the given ones. The old quantities come off
+// Part.units_left and the new ones go on, then every part touched on either
+// side has its averages rebuilt from the remaining history.
+pub async fn edit_purchase(
+    pool: SqlitePool,
+    purchase: Purchase,
+    parts: Vec<PartToSelect>,
+) -> Result<(), Errorr> {
+    let id = purchase.id;
+    let date = purchase.date;
+    let total = purchase.total;
+    let note = purchase.note;
+
+    let mut tx = pool.begin().await?;
+
+    let old_lines = sqlx::query!(
+        "
+        SELECT part_id, qty FROM PurchasePart
+        WHERE purchase_id = ?
+        ",
+        id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut touched: Vec<i64> = Vec::new();
+
+    for line in &old_lines {
+        sqlx::query!(
+            "
+            UPDATE Part
+            SET units_left = units_left - ?
+            WHERE part_id = ?
+            ",
+            line.qty,
+            line.part_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if !touched.contains(&line.part_id) {
+            touched.push(line.part_id);
+        }
+    }
+
+    sqlx::query!(
+        "
+        DELETE FROM PurchasePart
+        WHERE purchase_id = ?
+        ",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    for part in &parts {
+        let cost = part_cost(part)?;
+
+        sqlx::query!(
+            "
+            INSERT INTO PurchasePart (qty, cost, purchase_id, part_id)
+            VALUES (?,?,?,?)
+            ",
+            part.qty,
+            cost,
+            id,
+            part.part_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "
+            UPDATE Part
+            SET units_left = units_left + ?
+            WHERE part_id = ?
+            ",
+            part.qty,
+            part.part_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if !touched.contains(&part.part_id) {
+            touched.push(part.part_id);
+        }
+    }
+
+    for part_id in touched {
+        recompute_part_cost(&mut tx, part_id).await?;
+    }
+
+    recompute_product_costs(&mut tx).await?;
+
+    sqlx::query!(
+        "
+        UPDATE Purchase
+        SET date = ?, total = ?, note = ?
+        WHERE id = ?
+        ",
+        date,
+        total,
+        note,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn delete_purchase(pool: SqlitePool, purchase: Purchase) -> Result<(), Errorr> {
+    let id = purchase.id;
+
+    let mut tx = pool.begin().await?;
+
+    let lines = sqlx::query!(
+        "
+        SELECT part_id, qty FROM PurchasePart
+        WHERE purchase_id = ?
+        ",
+        id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+        DELETE FROM Purchase
+        WHERE id = ?
+        ",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    for line in &lines {
+        sqlx::query!(
+            "
+            UPDATE Part
+            SET units_left = units_left - ?
+            WHERE part_id = ?
+            ",
+            line.qty,
+            line.part_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        recompute_part_cost(&mut tx, line.part_id).await?;
+    }
+
+    recompute_product_costs(&mut tx).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
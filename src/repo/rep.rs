@@ -0,0 +1,436 @@
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{error::Errorr, repo::validate_date};
+
+#[derive(Default, Clone, Debug)]
+pub struct Rep {
+    pub id: i64,
+    pub name: String,
+    pub percentage: u8,
+    pub balance: f64,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct CommissionLine {
+    pub sale_id: i64,
+    pub date: String,
+    pub total: f64,
+    pub rep_cut: f64,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct RepPayout {
+    pub id: i64,
+    pub date: String,
+    pub amount: f64,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct RepAdjustment {
+    pub sale_id: Option<i64>,
+    pub date: String,
+    pub amount: f64,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct RepStatement {
+    pub lines: Vec<CommissionLine>,
+    pub adjustments: Vec<RepAdjustment>,
+    pub payouts: Vec<RepPayout>,
+}
+
+// The cut a paid-out sale was earning before a change, see `settle_paid_cut`.
+#[derive(Clone, Debug)]
+pub struct PaidCut {
+    pub rep_id: i64,
+    pub earned: f64,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct StatementRange {
+    pub from: String,
+    pub to: String,
+}
+
+impl StatementRange {
+    fn validate(&self) -> Result<(), Errorr> {
+        for date in [&self.from, &self.to] {
+            if !date.is_empty() {
+                validate_date(date)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn get_reps(pool: SqlitePool) -> Result<Vec<Rep>, Errorr> {
+    let reps = sqlx::query_as!(
+        Rep,
+        "
+        SELECT id as `id!`, name, percentage as `percentage: u8`,
+        IFNULL((SELECT SUM(rep_cut) FROM Sale
+            WHERE rep_id = Rep.id AND rep_payout_id IS NULL
+            AND status IN ('PAID', 'SHIPPED', 'COMPLETED')), 0.0)
+        + IFNULL((SELECT SUM(amount) FROM RepAdjustment
+            WHERE rep_id = Rep.id AND rep_payout_id IS NULL), 0.0)
+            as `balance!: f64`
+        FROM Rep
+        "
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(reps)
+}
+
+pub async fn get_rep(pool: SqlitePool, i: i64) -> Result<Rep, Errorr> {
+    let rep = sqlx::query_as!(
+        Rep,
+        "
+        SELECT id as `id!`, name, percentage as `percentage: u8`,
+        IFNULL((SELECT SUM(rep_cut) FROM Sale
+            WHERE rep_id = Rep.id AND rep_payout_id IS NULL
+            AND status IN ('PAID', 'SHIPPED', 'COMPLETED')), 0.0)
+        + IFNULL((SELECT SUM(amount) FROM RepAdjustment
+            WHERE rep_id = Rep.id AND rep_payout_id IS NULL), 0.0)
+            as `balance!: f64`
+        FROM Rep WHERE id = ?
+        ",
+        i
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(rep)
+}
+
+pub async fn add_rep(pool: SqlitePool, rep: Rep) -> Result<i64, Errorr> {
+    let name = rep.name;
+    let percentage = rep.percentage;
+
+    let r = sqlx::query!(
+        "
+        INSERT INTO Rep (name, percentage)
+        VALUES (?,?)
+        ",
+        name,
+        percentage
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(r.last_insert_rowid())
+}
+
+pub async fn edit_rep(pool: SqlitePool, rep: Rep) -> Result<(), Errorr> {
+    let id = rep.id;
+    let name = rep.name;
+    let percentage = rep.percentage;
+
+    sqlx::query!(
+        "
+        UPDATE Rep
+        SET name = ?, percentage = ?
+        WHERE id = ?
+        ",
+        name,
+        percentage,
+        id
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_rep(pool: SqlitePool, rep: Rep) -> Result<(), Errorr> {
+    let id = rep.id;
+
+    sqlx::query!(
+        "
+        DELETE FROM Rep
+        WHERE id = ?
+        ",
+        id
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| match Errorr::from(e) {
+        Errorr::ConstraintViolation(_) => Errorr::ConstraintViolation(format!(
+            "{} can't be deleted while sales still reference them",
+            rep.name
+        )),
+        e => e,
+    })?;
+
+    Ok(())
+}
+
+// Commission not yet covered by a payout, for sales dated in the range. A rep
+// earns the cut once the sale is paid, shipped or completed. Changes to sales
+// that were already paid out come in as adjustments dated when they happened.
+// An empty bound leaves that side open.
+pub async fn get_rep_statement(
+    pool: SqlitePool,
+    rep_id: i64,
+    range: StatementRange,
+) -> Result<RepStatement, Errorr> {
+    range.validate()?;
+
+    let from = range.from;
+    let to = range.to;
+
+    let lines = sqlx::query_as!(
+        CommissionLine,
+        "
+        SELECT sale_id as `sale_id!`, date, total, rep_cut as `rep_cut!: f64`
+        FROM Sale
+        WHERE rep_id = ? AND rep_cut IS NOT NULL AND rep_payout_id IS NULL
+        AND status IN ('PAID', 'SHIPPED', 'COMPLETED')
+        AND (? = '' OR date(date) >= date(?))
+        AND (? = '' OR date(date) <= date(?))
+        ORDER BY date
+        ",
+        rep_id,
+        from,
+        from,
+        to,
+        to
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let adjustments = sqlx::query_as!(
+        RepAdjustment,
+        "
+        SELECT sale_id, date, amount FROM RepAdjustment
+        WHERE rep_id = ? AND rep_payout_id IS NULL
+        AND (? = '' OR date(date) >= date(?))
+        AND (? = '' OR date(date) <= date(?))
+        ORDER BY date
+        ",
+        rep_id,
+        from,
+        from,
+        to,
+        to
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let payouts = sqlx::query_as!(
+        RepPayout,
+        "
+        SELECT id, date, amount FROM RepPayout
+        WHERE rep_id = ?
+        ORDER BY date DESC, id DESC
+        ",
+        rep_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let r = RepStatement {
+        lines,
+        adjustments,
+        payouts,
+    };
+
+    Ok(r)
+}
+
+// Pays out everything on the statement for the range and marks those sales and
+// adjustments as settled, so they drop off the next statement.
+pub async fn add_rep_payout(
+    pool: SqlitePool,
+    rep_id: i64,
+    range: StatementRange,
+    date: String,
+) -> Result<f64, Errorr> {
+    range.validate()?;
+    validate_date(&date)?;
+
+    let from = range.from;
+    let to = range.to;
+
+    let mut tx = pool.begin().await?;
+
+    let sale_ids = sqlx::query_scalar!(
+        "
+        SELECT sale_id FROM Sale
+        WHERE rep_id = ? AND rep_cut IS NOT NULL AND rep_payout_id IS NULL
+        AND status IN ('PAID', 'SHIPPED', 'COMPLETED')
+        AND (? = '' OR date(date) >= date(?))
+        AND (? = '' OR date(date) <= date(?))
+        ",
+        rep_id,
+        from,
+        from,
+        to,
+        to
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let adjustment_ids = sqlx::query_scalar!(
+        "
+        SELECT id FROM RepAdjustment
+        WHERE rep_id = ? AND rep_payout_id IS NULL
+        AND (? = '' OR date(date) >= date(?))
+        AND (? = '' OR date(date) <= date(?))
+        ",
+        rep_id,
+        from,
+        from,
+        to,
+        to
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if sale_ids.is_empty() && adjustment_ids.is_empty() {
+        return Err(Errorr::InvalidInput(String::from(
+            "There is no unpaid commission in that range",
+        )));
+    }
+
+    let r = sqlx::query!(
+        "
+        INSERT INTO RepPayout (rep_id, date, amount)
+        VALUES (?,?,0)
+        ",
+        rep_id,
+        date
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let payout_id = r.last_insert_rowid();
+
+    for sale_id in &sale_ids {
+        sqlx::query!(
+            "
+            UPDATE Sale
+            SET rep_payout_id = ?
+            WHERE sale_id = ?
+            ",
+            payout_id,
+            sale_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for adjustment_id in &adjustment_ids {
+        sqlx::query!(
+            "
+            UPDATE RepAdjustment
+            SET rep_payout_id = ?
+            WHERE id = ?
+            ",
+            payout_id,
+            adjustment_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let amount = sqlx::query_scalar!(
+        "
+        SELECT IFNULL((SELECT SUM(rep_cut) FROM Sale WHERE rep_payout_id = ?), 0.0)
+        + IFNULL((SELECT SUM(amount) FROM RepAdjustment WHERE rep_payout_id = ?), 0.0)
+        AS `amount!: f64`
+        ",
+        payout_id,
+        payout_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+        UPDATE RepPayout
+        SET amount = ?
+        WHERE id = ?
+        ",
+        amount,
+        payout_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(amount)
+}
+
+// What a sale already paid out to its rep currently earns: its cut while paid,
+// shipped or completed, nothing otherwise. None when it isn't paid out.
+pub async fn paid_cut(
+    conn: &mut SqliteConnection,
+    sale_id: i64,
+) -> Result<Option<PaidCut>, Errorr> {
+    let cut = sqlx::query_as!(
+        PaidCut,
+        "
+        SELECT rep_id as `rep_id!`,
+        CASE WHEN status IN ('PAID', 'SHIPPED', 'COMPLETED') THEN IFNULL(rep_cut, 0.0)
+        ELSE 0.0 END AS `earned!: f64`
+        FROM Sale
+        WHERE sale_id = ? AND rep_id IS NOT NULL AND rep_payout_id IS NOT NULL
+        ",
+        sale_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(cut)
+}
+
+// A payout's amount is fixed once made, so when a paid-out sale is edited,
+// cancelled or returned the difference in what it earns is written down as an
+// adjustment and settled on the rep's next payout. Without a date it's today.
+pub async fn settle_paid_cut(
+    conn: &mut SqliteConnection,
+    sale_id: i64,
+    before: Option<PaidCut>,
+    date: Option<&str>,
+) -> Result<(), Errorr> {
+    let Some(before) = before else {
+        return Ok(());
+    };
+    let after = paid_cut(&mut *conn, sale_id)
+        .await?
+        .map(|c| c.earned)
+        .unwrap_or(0.00);
+
+    add_adjustment(conn, before.rep_id, sale_id, after - before.earned, date).await
+}
+
+pub async fn add_adjustment(
+    conn: &mut SqliteConnection,
+    rep_id: i64,
+    sale_id: i64,
+    amount: f64,
+    date: Option<&str>,
+) -> Result<(), Errorr> {
+    if amount.abs() < 0.005 {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        "
+        INSERT INTO RepAdjustment (rep_id, sale_id, date, amount)
+        VALUES (?, ?, IFNULL(?, date('now', 'localtime')), ?)
+        ",
+        rep_id,
+        sale_id,
+        date,
+        amount
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
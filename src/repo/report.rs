@@ -0,0 +1,418 @@
+use std::{collections::HashMap, fmt};
+
+use sqlx::SqlitePool;
+
+use crate::{
+    error::Errorr,
+    repo::{sale::discount_off, validate_date},
+};
+
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub enum ReportGrouping {
+    Day,
+    Week,
+    #[default]
+    Month,
+    Client,
+    Product,
+    Rep,
+}
+
+impl ReportGrouping {
+    pub const ALL: [ReportGrouping; 6] = [
+        ReportGrouping::Day,
+        ReportGrouping::Week,
+        ReportGrouping::Month,
+        ReportGrouping::Client,
+        ReportGrouping::Product,
+        ReportGrouping::Rep,
+    ];
+}
+
+impl fmt::Display for ReportGrouping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ReportGrouping::Day => "Day",
+            ReportGrouping::Week => "Week",
+            ReportGrouping::Month => "Month",
+            ReportGrouping::Client => "Client",
+            ReportGrouping::Product => "Product",
+            ReportGrouping::Rep => "Rep",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct ReportFilter {
+    pub from: String,
+    pub to: String,
+    pub grouping: ReportGrouping,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct ReportRow {
+    pub label: String,
+    pub sales: i64,
+    pub revenue: f64,
+    pub returns: f64,
+    pub cogs: f64,
+    pub shipping: f64,
+    pub commission: f64,
+    pub net: f64,
+}
+
+impl ReportRow {
+    pub fn margin(&self) -> f64 {
+        if self.revenue == 0.00 {
+            0.00
+        } else {
+            self.net / self.revenue * 100.00
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ReportSale {
+    sale_id: i64,
+    day: Option<String>,
+    week: Option<String>,
+    month: Option<String>,
+    client_name: String,
+    rep_name: String,
+    total: f64,
+    cost: f64,
+    shipping: f64,
+    carrier_cost: f64,
+    rep_cut: Option<f64>,
+    net: f64,
+}
+
+#[derive(Clone, Debug)]
+struct ReportLine {
+    sale_id: i64,
+    name: String,
+    qty: i64,
+    cost_at_sale: f64,
+    msrp_at_sale: f64,
+    discount: f64,
+    discount_percent: bool,
+}
+
+impl ReportLine {
+    fn line_total(&self) -> f64 {
+        let amount = self.msrp_at_sale * self.qty as f64;
+        amount - discount_off(amount, self.discount, self.discount_percent)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ReportReturn {
+    id: i64,
+    day: Option<String>,
+    week: Option<String>,
+    month: Option<String>,
+    client_name: String,
+    rep_name: String,
+    refund: f64,
+    restocked_cost: f64,
+    rep_cut_reduction: f64,
+}
+
+#[derive(Clone, Debug)]
+struct ReportReturnLine {
+    return_id: i64,
+    name: String,
+    qty: i64,
+    restocked: bool,
+    cost_at_sale: f64,
+    msrp_at_sale: f64,
+    line_qty: i64,
+    discount: f64,
+    discount_percent: bool,
+}
+
+impl ReportReturnLine {
+    // What one unit sold for after its line discount.
+    fn unit_price(&self) -> f64 {
+        let amount = self.msrp_at_sale * self.line_qty as f64;
+        let total = amount - discount_off(amount, self.discount, self.discount_percent);
+        if self.line_qty == 0 {
+            0.00
+        } else {
+            total / self.line_qty as f64
+        }
+    }
+}
+
+fn add_to(rows: &mut Vec<ReportRow>, label: String, row: ReportRow) {
+    match rows.iter_mut().find(|r| r.label == label) {
+        Some(r) => {
+            r.sales += row.sales;
+            r.revenue += row.revenue;
+            r.returns += row.returns;
+            r.cogs += row.cogs;
+            r.shipping += row.shipping;
+            r.commission += row.commission;
+            r.net += row.net;
+        }
+        None => rows.push(ReportRow { label, ..row }),
+    }
+}
+
+// A return gives back its refund, takes the cost of restocked units off COGS
+// and the rep's share off commission.
+fn return_row(refund: f64, restocked_cost: f64, rep_cut_reduction: f64) -> ReportRow {
+    ReportRow {
+        returns: refund,
+        cogs: -restocked_cost,
+        commission: -rep_cut_reduction,
+        net: restocked_cost + rep_cut_reduction - refund,
+        ..Default::default()
+    }
+}
+
+// Everything comes from the values stored on the sale and its lines when it
+// was submitted, so later cost changes don't rewrite history. Drafts and
+// cancelled sales are left out. Sales are reported as sold and returns are
+// booked to the day they came back, so a return can land in a later period
+// than its sale.
+pub async fn get_report(pool: SqlitePool, filter: ReportFilter) -> Result<Vec<ReportRow>, Errorr> {
+    for date in [&filter.from, &filter.to] {
+        if !date.is_empty() {
+            validate_date(date)?;
+        }
+    }
+
+    let from = filter.from;
+    let to = filter.to;
+
+    // The stored cost, cut and net already have returns taken off, they're
+    // added back here since returns are reported on their own.
+    let sales = sqlx::query_as!(
+        ReportSale,
+        "
+        SELECT Sale.sale_id, date(Sale.date) AS day, strftime('%Y-W%W', Sale.date) AS week,
+        strftime('%Y-%m', Sale.date) AS month, Client.name AS client_name,
+        IFNULL(Rep.name, 'No Rep') AS `rep_name!: String`,
+        Sale.total, Sale.cost + IFNULL(R.restocked_cost, 0.0) AS `cost!: f64`, Sale.shipping,
+        IFNULL(Setting.carrier_cost, 0.0) AS `carrier_cost!: f64`,
+        Sale.rep_cut + IFNULL(R.rep_cut_reduction, 0.0) AS `rep_cut: f64`,
+        Sale.net + IFNULL(R.refund - R.restocked_cost - R.rep_cut_reduction, 0.0) AS `net!: f64`
+        FROM Sale
+        JOIN Client ON Sale.client_id = Client.client_id
+        LEFT JOIN Rep ON Sale.rep_id = Rep.id
+        LEFT JOIN Setting ON Sale.setting_id = Setting.id
+        LEFT JOIN (SELECT sale_id, SUM(refund) AS refund, SUM(restocked_cost) AS restocked_cost,
+        SUM(rep_cut_reduction) AS rep_cut_reduction
+        FROM SaleReturn GROUP BY sale_id) AS R ON R.sale_id = Sale.sale_id
+        WHERE Sale.status NOT IN ('DRAFT', 'CANCELLED')
+        AND (? = '' OR date(Sale.date) >= date(?))
+        AND (? = '' OR date(Sale.date) <= date(?))
+        ",
+        from,
+        from,
+        to,
+        to
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let returns = sqlx::query_as!(
+        ReportReturn,
+        "
+        SELECT SaleReturn.id, date(SaleReturn.date) AS day,
+        strftime('%Y-W%W', SaleReturn.date) AS week, strftime('%Y-%m', SaleReturn.date) AS month,
+        Client.name AS client_name, IFNULL(Rep.name, 'No Rep') AS `rep_name!: String`,
+        SaleReturn.refund, SaleReturn.restocked_cost, SaleReturn.rep_cut_reduction
+        FROM SaleReturn
+        JOIN Sale ON SaleReturn.sale_id = Sale.sale_id
+        JOIN Client ON Sale.client_id = Client.client_id
+        LEFT JOIN Rep ON Sale.rep_id = Rep.id
+        WHERE Sale.status NOT IN ('DRAFT', 'CANCELLED')
+        AND (? = '' OR date(SaleReturn.date) >= date(?))
+        AND (? = '' OR date(SaleReturn.date) <= date(?))
+        ",
+        from,
+        from,
+        to,
+        to
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let mut rows: Vec<ReportRow> = Vec::new();
+
+    if filter.grouping == ReportGrouping::Product {
+        let lines = sqlx::query_as!(
+            ReportLine,
+            "
+            SELECT SaleProduct.sale_id, Product.name, SaleProduct.qty,
+            SaleProduct.cost_at_sale, SaleProduct.msrp_at_sale, SaleProduct.discount,
+            SaleProduct.discount_percent
+            FROM SaleProduct
+            JOIN Product ON SaleProduct.product_id = Product.product_id
+            JOIN Sale ON SaleProduct.sale_id = Sale.sale_id
+            WHERE Sale.status NOT IN ('DRAFT', 'CANCELLED')
+            AND (? = '' OR date(Sale.date) >= date(?))
+            AND (? = '' OR date(Sale.date) <= date(?))
+            ",
+            from,
+            from,
+            to,
+            to
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        let sales_by_id: HashMap<i64, &ReportSale> = sales.iter().map(|s| (s.sale_id, s)).collect();
+        let mut lines_totals: HashMap<i64, f64> = HashMap::new();
+        for line in &lines {
+            *lines_totals.entry(line.sale_id).or_default() += line.line_total();
+        }
+
+        // Shipping, carrier cost, commission and the sale discount belong to
+        // the whole sale, so each line carries its share of them by revenue.
+        for line in &lines {
+            let sale = match sales_by_id.get(&line.sale_id) {
+                Some(s) => s,
+                None => continue,
+            };
+            let product_total = sale.total - sale.shipping;
+            let lines_total = lines_totals.get(&line.sale_id).copied().unwrap_or(0.00);
+            let share = if lines_total == 0.00 {
+                0.00
+            } else {
+                line.line_total() / lines_total
+            };
+            let revenue = product_total * share;
+            let shipping = sale.shipping * share;
+            let cogs = line.cost_at_sale * line.qty as f64 + sale.carrier_cost * share;
+            let commission = sale.rep_cut.unwrap_or(0.00) * share;
+
+            add_to(
+                &mut rows,
+                line.name.clone(),
+                ReportRow {
+                    sales: 1,
+                    revenue: revenue + shipping,
+                    cogs,
+                    shipping,
+                    commission,
+                    net: revenue + shipping - cogs - commission,
+                    ..Default::default()
+                },
+            );
+        }
+
+        // A return's refund and the rep's share are spread over the products
+        // that came back by what they sold for.
+        let return_lines = sqlx::query_as!(
+            ReportReturnLine,
+            "
+            SELECT SaleReturnProduct.return_id, Product.name, SaleReturnProduct.qty,
+            SaleReturnProduct.restocked, SaleProduct.cost_at_sale AS `cost_at_sale!: f64`,
+            SaleProduct.msrp_at_sale AS `msrp_at_sale!: f64`, SaleProduct.qty AS `line_qty!: i64`,
+            SaleProduct.discount AS `discount!: f64`,
+            SaleProduct.discount_percent AS `discount_percent!: bool`
+            FROM SaleReturnProduct
+            JOIN SaleReturn ON SaleReturnProduct.return_id = SaleReturn.id
+            JOIN Product ON SaleReturnProduct.product_id = Product.product_id
+            JOIN SaleProduct ON SaleProduct.sale_id = SaleReturn.sale_id
+            AND SaleProduct.product_id = SaleReturnProduct.product_id
+            JOIN Sale ON SaleReturn.sale_id = Sale.sale_id
+            WHERE Sale.status NOT IN ('DRAFT', 'CANCELLED')
+            AND (? = '' OR date(SaleReturn.date) >= date(?))
+            AND (? = '' OR date(SaleReturn.date) <= date(?))
+            ",
+            from,
+            from,
+            to,
+            to
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        let mut return_weights: HashMap<i64, f64> = HashMap::new();
+        for line in &return_lines {
+            *return_weights.entry(line.return_id).or_default() +=
+                line.unit_price() * line.qty as f64;
+        }
+        let returns_by_id: HashMap<i64, &ReportReturn> =
+            returns.iter().map(|r| (r.id, r)).collect();
+
+        for line in &return_lines {
+            let ret = match returns_by_id.get(&line.return_id) {
+                Some(r) => r,
+                None => continue,
+            };
+            let weight = return_weights.get(&line.return_id).copied().unwrap_or(0.00);
+            let share = if weight == 0.00 {
+                0.00
+            } else {
+                line.unit_price() * line.qty as f64 / weight
+            };
+            let restocked_cost = if line.restocked {
+                line.cost_at_sale * line.qty as f64
+            } else {
+                0.00
+            };
+
+            add_to(
+                &mut rows,
+                line.name.clone(),
+                return_row(
+                    ret.refund * share,
+                    restocked_cost,
+                    ret.rep_cut_reduction * share,
+                ),
+            );
+        }
+    } else {
+        for sale in &sales {
+            let label = match filter.grouping {
+                ReportGrouping::Day => sale.day.clone(),
+                ReportGrouping::Week => sale.week.clone(),
+                ReportGrouping::Month => sale.month.clone(),
+                ReportGrouping::Client => Some(sale.client_name.clone()),
+                ReportGrouping::Rep => Some(sale.rep_name.clone()),
+                ReportGrouping::Product => None,
+            };
+
+            add_to(
+                &mut rows,
+                label.unwrap_or(String::from("Undated")),
+                ReportRow {
+                    sales: 1,
+                    revenue: sale.total,
+                    cogs: sale.cost,
+                    shipping: sale.shipping,
+                    commission: sale.rep_cut.unwrap_or(0.00),
+                    net: sale.net,
+                    ..Default::default()
+                },
+            );
+        }
+
+        for ret in &returns {
+            let label = match filter.grouping {
+                ReportGrouping::Day => ret.day.clone(),
+                ReportGrouping::Week => ret.week.clone(),
+                ReportGrouping::Month => ret.month.clone(),
+                ReportGrouping::Client => Some(ret.client_name.clone()),
+                ReportGrouping::Rep => Some(ret.rep_name.clone()),
+                ReportGrouping::Product => None,
+            };
+
+            add_to(
+                &mut rows,
+                label.unwrap_or(String::from("Undated")),
+                return_row(ret.refund, ret.restocked_cost, ret.rep_cut_reduction),
+            );
+        }
+    }
+
+    rows.sort_by(|a, b| a.label.cmp(&b.label));
+
+    Ok(rows)
+}
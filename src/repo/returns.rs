@@ -0,0 +1,307 @@
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    error::Errorr,
+    repo::{
+        rep::{paid_cut, settle_paid_cut},
+        sale::{record_status, sale_status, Sale, SaleProductToAdd, SaleStatus},
+        validate_date,
+    },
+};
+
+#[derive(Clone, Debug)]
+pub struct SaleReturn {
+    pub id: i64,
+    pub date: String,
+    pub refund: f64,
+    pub note: Option<String>,
+    pub items: String,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct ReturnLine {
+    pub product_id: i64,
+    pub name: String,
+    pub qty: i64,
+    pub restock: bool,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct ReturnToAdd {
+    pub date: String,
+    pub refund: String,
+    pub note: String,
+    pub lines: Vec<ReturnLine>,
+}
+
+pub async fn get_sale_returns(pool: SqlitePool, sale_id: i64) -> Result<Vec<SaleReturn>, Errorr> {
+    let returns = sqlx::query_as!(
+        SaleReturn,
+        "
+        SELECT SaleReturn.id, SaleReturn.date, SaleReturn.refund, SaleReturn.note,
+        IFNULL(GROUP_CONCAT(Product.name || ' x' || SaleReturnProduct.qty ||
+        CASE WHEN SaleReturnProduct.restocked THEN ' restocked' ELSE ' written off' END, ', '), '')
+        AS `items!: String`
+        FROM SaleReturn
+        LEFT JOIN SaleReturnProduct ON SaleReturnProduct.return_id = SaleReturn.id
+        LEFT JOIN Product ON SaleReturnProduct.product_id = Product.product_id
+        WHERE SaleReturn.sale_id = ?
+        GROUP BY SaleReturn.id
+        ORDER BY SaleReturn.id
+        ",
+        sale_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(returns)
+}
+
+// An edit can't take a line below what has already come back on it.
+pub async fn check_returned_lines(
+    conn: &mut SqliteConnection,
+    sale_id: i64,
+    items: &[SaleProductToAdd],
+) -> Result<(), Errorr> {
+    let returned = sqlx::query!(
+        "
+        SELECT SaleReturnProduct.product_id, Product.name, SUM(SaleReturnProduct.qty) AS `qty!: i64`
+        FROM SaleReturnProduct
+        JOIN SaleReturn ON SaleReturnProduct.return_id = SaleReturn.id
+        JOIN Product ON SaleReturnProduct.product_id = Product.product_id
+        WHERE SaleReturn.sale_id = ?
+        GROUP BY SaleReturnProduct.product_id
+        ",
+        sale_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for r in &returned {
+        let qty = items
+            .iter()
+            .filter(|i| i.product_id == r.product_id)
+            .map(|i| i.qty)
+            .sum::<i64>();
+        if qty < r.qty {
+            return Err(Errorr::InvalidInput(format!(
+                "{} has {} units returned, its line can't go below that",
+                r.name, r.qty
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// Totals worked out from the lines know nothing of returns, so the stored
+// refunds, recovered cost and rep cut reductions are taken off again.
+pub async fn apply_returns(conn: &mut SqliteConnection, sale: &mut Sale) -> Result<(), Errorr> {
+    let r = sqlx::query!(
+        "
+        SELECT IFNULL(SUM(refund), 0.0) AS `refund!: f64`,
+        IFNULL(SUM(restocked_cost), 0.0) AS `restocked_cost!: f64`,
+        IFNULL(SUM(rep_cut_reduction), 0.0) AS `rep_cut_reduction!: f64`
+        FROM SaleReturn
+        WHERE sale_id = ?
+        ",
+        sale.sale_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let reduction = sale
+        .rep_cut
+        .map(|c| r.rep_cut_reduction.min(c))
+        .unwrap_or(0.00);
+
+    sale.cost -= r.restocked_cost;
+    sale.rep_cut = sale.rep_cut.map(|c| c - reduction);
+    sale.net += r.restocked_cost + reduction - r.refund;
+
+    Ok(())
+}
+
+// Restocked units go back to Product.units and their cost comes off the sale,
+// written off units stay a cost. The rep gives up the share of their cut the
+// refund is of the product total, on a sale already paid out to them that
+// comes off their next payout. Returning everything left on the sale marks it
+// Returned.
+pub async fn add_return(pool: SqlitePool, sale_id: i64, ret: ReturnToAdd) -> Result<bool, Errorr> {
+    validate_date(&ret.date)?;
+
+    let refund = ret
+        .refund
+        .parse::<f64>()
+        .ok()
+        .filter(|r| *r >= 0.00)
+        .ok_or(Errorr::InvalidInput(format!(
+            "\"{}\" is not a valid refund amount",
+            ret.refund
+        )))?;
+    let lines: Vec<&ReturnLine> = ret.lines.iter().filter(|l| l.qty > 0).collect();
+    if lines.is_empty() {
+        return Err(Errorr::InvalidInput(String::from(
+            "Pick at least one unit to return",
+        )));
+    }
+    let note = Some(ret.note.trim()).filter(|n| !n.is_empty());
+    let date = ret.date.as_str();
+
+    let mut tx = pool.begin().await?;
+
+    let status = sale_status(&mut tx, sale_id).await?;
+    if !matches!(status, SaleStatus::Shipped | SaleStatus::Completed) {
+        return Err(Errorr::InvalidInput(format!(
+            "Sale #{} is {}, only shipped or completed sales take returns",
+            sale_id, status
+        )));
+    }
+
+    let sale_lines = sqlx::query!(
+        "
+        SELECT SaleProduct.product_id, SaleProduct.qty, SaleProduct.cost_at_sale,
+        IFNULL((SELECT SUM(SaleReturnProduct.qty) FROM SaleReturnProduct
+        JOIN SaleReturn ON SaleReturnProduct.return_id = SaleReturn.id
+        WHERE SaleReturn.sale_id = SaleProduct.sale_id
+        AND SaleReturnProduct.product_id = SaleProduct.product_id), 0) AS `returned!: i64`
+        FROM SaleProduct
+        WHERE SaleProduct.sale_id = ?
+        ",
+        sale_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut restocked_cost = 0.00;
+    for line in &lines {
+        let sale_line = sale_lines
+            .iter()
+            .find(|l| l.product_id == line.product_id)
+            .ok_or(Errorr::NotFound(format!(
+                "{} is not on sale #{}",
+                line.name, sale_id
+            )))?;
+        let left = sale_line.qty - sale_line.returned;
+        if line.qty > left {
+            return Err(Errorr::InvalidInput(format!(
+                "Only {} of {} can still be returned",
+                left, line.name
+            )));
+        }
+        if line.restock {
+            restocked_cost += sale_line.cost_at_sale * line.qty as f64;
+        }
+    }
+
+    let sale = sqlx::query!(
+        "
+        SELECT total, shipping, refunded, rep_cut,
+        IFNULL((SELECT SUM(amount) FROM Payment WHERE Payment.sale_id = Sale.sale_id), 0.0)
+            AS `paid!: f64`
+        FROM Sale
+        WHERE sale_id = ?
+        ",
+        sale_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Only money the client actually paid can go back to them.
+    let refundable = (sale.paid - sale.refunded).max(0.00);
+    if refund > refundable + 0.005 {
+        return Err(Errorr::InvalidInput(format!(
+            "${:.2} is more than the ${:.2} paid and not yet refunded on sale #{}",
+            refund, refundable, sale_id
+        )));
+    }
+
+    let product_total = sale.total - sale.shipping;
+    let rep_cut_reduction = match sale.rep_cut {
+        Some(c) if product_total > 0.00 => (c * refund / product_total).min(c),
+        _ => 0.00,
+    };
+
+    let before = paid_cut(&mut tx, sale_id).await?;
+
+    let r = sqlx::query!(
+        "
+        INSERT INTO SaleReturn (sale_id, date, refund, restocked_cost, rep_cut_reduction, note)
+        VALUES (?,?,?,?,?,?)
+        ",
+        sale_id,
+        date,
+        refund,
+        restocked_cost,
+        rep_cut_reduction,
+        note
+    )
+    .execute(&mut *tx)
+    .await?;
+    let return_id = r.last_insert_rowid();
+
+    for line in &lines {
+        sqlx::query!(
+            "
+            INSERT INTO SaleReturnProduct (return_id, product_id, qty, restocked)
+            VALUES (?,?,?,?)
+            ",
+            return_id,
+            line.product_id,
+            line.qty,
+            line.restock
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if line.restock {
+            sqlx::query!(
+                "
+                UPDATE Product
+                SET units = units + ?
+                WHERE product_id = ?
+                ",
+                line.qty,
+                line.product_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    sqlx::query!(
+        "
+        UPDATE Sale
+        SET refunded = refunded + ?, cost = cost - ?, rep_cut = rep_cut - ?,
+        net = net - ? + ? + ?
+        WHERE sale_id = ?
+        ",
+        refund,
+        restocked_cost,
+        rep_cut_reduction,
+        refund,
+        restocked_cost,
+        rep_cut_reduction,
+        sale_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let all_returned = sale_lines.iter().all(|l| {
+        let qty: i64 = lines
+            .iter()
+            .filter(|r| r.product_id == l.product_id)
+            .map(|r| r.qty)
+            .sum();
+        l.returned + qty >= l.qty
+    });
+    let mark_returned = all_returned && status.next().contains(&SaleStatus::Returned);
+    if mark_returned {
+        record_status(&mut tx, sale_id, Some(status), SaleStatus::Returned).await?;
+    }
+    settle_paid_cut(&mut tx, sale_id, before, Some(date)).await?;
+
+    tx.commit().await?;
+
+    Ok(mark_returned)
+}
@@ -0,0 +1,1109 @@
+use std::fmt;
+
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    error::Errorr,
+    repo::{
+        client::{get_client, get_clients, Client},
+        product::{get_products, Product},
+        rep::{add_adjustment, get_reps, paid_cut, settle_paid_cut, Rep},
+        returns::{apply_returns, check_returned_lines, get_sale_returns, SaleReturn},
+        setting::{sale_setting, setting_on, Setting},
+        shortage_message, validate_date, Shortage,
+    },
+};
+
+// Stored as the uppercase variant name, the strings the status column has
+// always held.
+#[derive(Default, Clone, Copy, Debug, PartialEq, sqlx::Type)]
+#[sqlx(rename_all = "UPPERCASE")]
+pub enum SaleStatus {
+    #[default]
+    Draft,
+    Confirmed,
+    Paid,
+    Shipped,
+    Completed,
+    Cancelled,
+    Returned,
+}
+
+impl SaleStatus {
+    // Payment and shipping can happen in either order, cancelling is only
+    // possible before the goods go out and returning only after.
+    pub fn next(&self) -> &'static [SaleStatus] {
+        match self {
+            SaleStatus::Draft => &[SaleStatus::Confirmed, SaleStatus::Cancelled],
+            SaleStatus::Confirmed => {
+                &[SaleStatus::Paid, SaleStatus::Shipped, SaleStatus::Cancelled]
+            }
+            SaleStatus::Paid => &[
+                SaleStatus::Shipped,
+                SaleStatus::Completed,
+                SaleStatus::Cancelled,
+            ],
+            SaleStatus::Shipped => &[
+                SaleStatus::Paid,
+                SaleStatus::Completed,
+                SaleStatus::Returned,
+            ],
+            SaleStatus::Completed => &[SaleStatus::Returned],
+            SaleStatus::Cancelled | SaleStatus::Returned => &[],
+        }
+    }
+
+    // Cancelled and returned sales have put their units back on the shelf.
+    pub fn holds_stock(&self) -> bool {
+        !matches!(self, SaleStatus::Cancelled | SaleStatus::Returned)
+    }
+}
+
+impl fmt::Display for SaleStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SaleStatus::Draft => "Draft",
+            SaleStatus::Confirmed => "Confirmed",
+            SaleStatus::Paid => "Paid",
+            SaleStatus::Shipped => "Shipped",
+            SaleStatus::Completed => "Completed",
+            SaleStatus::Cancelled => "Cancelled",
+            SaleStatus::Returned => "Returned",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SaleStatusChange {
+    pub from_status: Option<SaleStatus>,
+    pub to_status: SaleStatus,
+    pub changed_at: String,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct SaleProduct {
+    pub product_id: i64,
+    pub name: String,
+    pub qty: i64,
+    pub units: i64,
+    pub msrp: f64,
+    pub cost: f64,
+    pub cost_at_sale: f64,
+    pub msrp_at_sale: f64,
+    pub discount: f64,
+    pub discount_percent: bool,
+    pub returned: i64,
+    pub written_off: i64,
+}
+
+impl SaleProduct {
+    pub fn line_total(&self) -> f64 {
+        let amount = self.msrp_at_sale * self.qty as f64;
+        amount - discount_off(amount, self.discount, self.discount_percent)
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct SaleProductToAdd {
+    pub product_id: i64,
+    pub name: String,
+    pub qty: i64,
+    pub msrp: f64,
+    pub cost: f64,
+    pub units: i64,
+    pub discount: f64,
+    pub discount_input: String,
+    pub discount_percent: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Sale {
+    pub sale_id: i64,
+    pub discount: Option<f64>,
+    pub discount_input: String,
+    pub discount_percent: bool,
+    pub discount_amount: f64,
+    pub total: f64,
+    pub cost: f64,
+    pub net: f64,
+    pub date: String,
+    pub client_id: i64,
+    pub client_name: String,
+    pub note: Option<String>,
+    pub rep_id: Option<i64>,
+    pub rep_name: String,
+    pub rep_percentage: u8,
+    pub rep_cut: Option<f64>,
+    pub status: SaleStatus,
+    pub shipping: f64,
+    pub backorder_reason: Option<String>,
+    pub paid: f64,
+    pub refunded: f64,
+}
+
+impl Sale {
+    // A refund is money paid back out of what the client paid, not a credit
+    // against what they owe, so it doesn't change the balance.
+    pub fn balance(&self) -> f64 {
+        self.total - self.paid
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SaleH {
+    pub sale_id: i64,
+    pub discount: Option<f64>,
+    pub total: f64,
+    pub cost: f64,
+    pub net: f64,
+    pub date: String,
+    pub client_id: i64,
+    pub rep_cut: Option<f64>,
+    pub status: SaleStatus,
+    pub shipping: f64,
+    pub rep_id: Option<i64>,
+    pub note: Option<String>,
+    pub backorder_reason: Option<String>,
+    pub setting_id: Option<i64>,
+    pub rep_payout_id: Option<i64>,
+    pub refunded: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SalesSummary {
+    pub sales: i64,
+    pub revenue: f64,
+    pub net: f64,
+    pub units_sold: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct SC {
+    pub sale_products: Vec<SaleProduct>,
+    pub client: Client,
+    pub history: Vec<SaleStatusChange>,
+    pub returns: Vec<SaleReturn>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PCR {
+    pub products: Vec<Product>,
+    pub clients: Vec<Client>,
+    pub reps: Vec<Rep>,
+}
+
+// Products whose stock would go negative if the sale went through.
+pub fn sale_shortages(products: &[SaleProductToAdd]) -> Vec<Shortage> {
+    products
+        .iter()
+        .filter(|p| p.qty > p.units)
+        .map(|p| Shortage {
+            name: p.name.clone(),
+            needed: p.qty,
+            available: p.units,
+        })
+        .collect()
+}
+
+// What a discount takes off an amount. A percentage is of the amount, a fixed
+// discount is in dollars, and neither can take it below zero.
+pub fn discount_off(amount: f64, discount: f64, percent: bool) -> f64 {
+    let off = if percent {
+        amount * discount / 100.00
+    } else {
+        discount
+    };
+
+    off.clamp(0.00, amount.max(0.00))
+}
+
+fn check_discount(discount: f64, percent: bool, name: &str) -> Result<(), Errorr> {
+    if discount < 0.00 || (percent && discount > 100.00) {
+        return Err(Errorr::InvalidInput(format!(
+            "{} is not a valid discount for {}",
+            if percent {
+                format!("{}%", discount)
+            } else {
+                format!("${:.2}", discount)
+            },
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+// Discounts are typed in as text and only read here, an empty one is no
+// discount.
+fn check_discounts(sale: &mut Sale, items: &mut [SaleProductToAdd]) -> Result<(), Errorr> {
+    let input = sale.discount_input.trim();
+    sale.discount = if input.is_empty() {
+        None
+    } else {
+        let discount = input.parse::<f64>().map_err(|_| {
+            Errorr::InvalidInput(format!(
+                "\"{}\" is not a valid discount for the sale",
+                input
+            ))
+        })?;
+        Some(discount).filter(|d| *d != 0.00)
+    };
+
+    for item in items.iter_mut() {
+        let input = item.discount_input.trim();
+        item.discount = if input.is_empty() {
+            0.00
+        } else {
+            input.parse::<f64>().map_err(|_| {
+                Errorr::InvalidInput(format!(
+                    "\"{}\" is not a valid discount for {}",
+                    input, item.name
+                ))
+            })?
+        };
+        check_discount(item.discount, item.discount_percent, &item.name)?;
+    }
+
+    check_discount(
+        sale.discount.unwrap_or(0.00),
+        sale.discount_percent,
+        "the sale",
+    )
+}
+
+// Going short on stock is an explicit choice and always comes with a reason.
+fn check_backorder(sale: &Sale, allow_backorder: bool) -> Result<(), Errorr> {
+    if allow_backorder && sale.backorder_reason.is_none() {
+        return Err(Errorr::InvalidInput(String::from(
+            "Give a reason to sell more than is in stock",
+        )));
+    }
+
+    Ok(())
+}
+
+// Totals, shipping and rep cut for a sale with the given lines. Orders at or
+// over the free shipping threshold ship free, otherwise the client pays the
+// shipping charge, and every order costs us the carrier cost. Line discounts
+// come off first and the sale discount off what's left, so the threshold and
+// the rep cut both go by what the client actually pays for the products.
+fn compute_totals(sale: &mut Sale, items: &[SaleProductToAdd], setting: &Setting) {
+    sale.total = 0.00;
+    sale.cost = 0.00;
+    sale.net = 0.00;
+    sale.discount_amount = 0.00;
+    sale.rep_cut = None;
+
+    for item in items {
+        let amount = item.msrp * item.qty as f64;
+        let off = discount_off(amount, item.discount, item.discount_percent);
+        let total = amount - off;
+        let cost = item.cost * item.qty as f64;
+        sale.total += total;
+        sale.cost += cost;
+        sale.net += total - cost;
+        sale.discount_amount += off;
+    }
+
+    let off = discount_off(
+        sale.total,
+        sale.discount.unwrap_or(0.00),
+        sale.discount_percent,
+    );
+    sale.total -= off;
+    sale.net -= off;
+    sale.discount_amount += off;
+
+    if sale.total >= setting.free_shipping_threshold {
+        sale.shipping = 0.00;
+    } else {
+        sale.shipping = setting.shipping;
+    }
+
+    if sale.rep_id.is_some() {
+        let rep_cut = sale.total * (sale.rep_percentage as f64 / 100.00);
+        sale.net -= rep_cut;
+        sale.rep_cut = Some(rep_cut);
+    }
+
+    sale.total += sale.shipping;
+    sale.cost += setting.carrier_cost;
+    sale.net += sale.shipping - setting.carrier_cost;
+}
+
+// Re-checks the lines against the stored units, the form may have been open a
+// while.
+async fn check_stock(
+    conn: &mut SqliteConnection,
+    items: &[SaleProductToAdd],
+) -> Result<(), Errorr> {
+    let mut products = items.to_vec();
+    for p in products.iter_mut() {
+        p.units = sqlx::query_scalar!(
+            "SELECT units FROM Product WHERE product_id = ?",
+            p.product_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+    }
+
+    let shortages = sale_shortages(&products);
+    if !shortages.is_empty() {
+        return Err(Errorr::InsufficientStock(shortage_message(&shortages)));
+    }
+
+    Ok(())
+}
+
+async fn insert_lines(
+    conn: &mut SqliteConnection,
+    sale_id: i64,
+    items: &[SaleProductToAdd],
+    take_stock: bool,
+) -> Result<(), Errorr> {
+    for item in items {
+        sqlx::query!(
+            "
+            INSERT INTO SaleProduct ( sale_id, qty, product_id, cost_at_sale, msrp_at_sale, discount, discount_percent )
+            VALUES (?,?,?,?,?,?,?)
+            ",
+            sale_id,
+            item.qty,
+            item.product_id,
+            item.cost,
+            item.msrp,
+            item.discount,
+            item.discount_percent
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        if take_stock {
+            sqlx::query!(
+                "
+                UPDATE Product
+                SET units = units - ?
+                WHERE product_id = ?
+                ",
+                item.qty,
+                item.product_id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn get_sales(pool: SqlitePool) -> Result<Vec<Sale>, Errorr> {
+    let sales = sqlx::query_as!(Sale,
+                                "SELECT Sale.sale_id as `sale_id!`, discount, '' as `discount_input!: String`, discount_percent, discount_amount, total, Sale.cost, Sale.client_id as `client_id!`, net, date, note, rep_id, shipping, status as `status: SaleStatus`, rep_cut, backorder_reason, refunded,
+                                Client.name as client_name,
+                                IFNULL(Rep.name, '') as `rep_name!: String`, IFNULL(Sale.rep_percentage, IFNULL(Rep.percentage, 0)) as `rep_percentage!: u8`,
+                                IFNULL((SELECT SUM(amount) FROM Payment WHERE Payment.sale_id = Sale.sale_id), 0.0) as `paid!: f64`
+                                FROM Sale
+                                JOIN Client ON Sale.client_id = Client.client_id
+                                LEFT JOIN Rep ON Sale.rep_id = Rep.id
+                                "
+                               )
+        .fetch_all(&pool)
+        .await?;
+
+    Ok(sales)
+}
+
+// Sales dated from the start of this month up to today, drafts and cancelled
+// sales excluded, with refunds and returned units taken off. Dates that aren't
+// YYYY-MM-DD can't be placed in a month and are left out.
+pub async fn get_month_to_date(pool: SqlitePool) -> Result<SalesSummary, Errorr> {
+    let totals = sqlx::query!(
+        "
+        SELECT COUNT(*) AS `sales!: i64`, IFNULL(SUM(total - refunded), 0.0) AS `revenue!: f64`,
+        IFNULL(SUM(net), 0.0) AS `net!: f64`
+        FROM Sale
+        WHERE status NOT IN ('DRAFT', 'CANCELLED')
+        AND date(date) >= date('now', 'localtime', 'start of month')
+        AND date(date) <= date('now', 'localtime')
+        "
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    let units_sold = sqlx::query_scalar!(
+        "
+        SELECT IFNULL(SUM(SaleProduct.qty), 0)
+        - IFNULL((SELECT SUM(SaleReturnProduct.qty) FROM SaleReturnProduct
+        JOIN SaleReturn ON SaleReturnProduct.return_id = SaleReturn.id
+        JOIN Sale ON Sale.sale_id = SaleReturn.sale_id
+        WHERE Sale.status NOT IN ('DRAFT', 'CANCELLED')
+        AND date(Sale.date) >= date('now', 'localtime', 'start of month')
+        AND date(Sale.date) <= date('now', 'localtime')), 0) AS `units!: i64`
+        FROM SaleProduct
+        JOIN Sale ON Sale.sale_id = SaleProduct.sale_id
+        WHERE Sale.status NOT IN ('DRAFT', 'CANCELLED')
+        AND date(Sale.date) >= date('now', 'localtime', 'start of month')
+        AND date(Sale.date) <= date('now', 'localtime')
+        "
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    let r = SalesSummary {
+        sales: totals.sales,
+        revenue: totals.revenue,
+        net: totals.net,
+        units_sold,
+    };
+
+    Ok(r)
+}
+
+// Sales that still need work before they're completed.
+pub async fn get_open_sales(pool: SqlitePool) -> Result<Vec<SaleH>, Errorr> {
+    let sales = sqlx::query_as!(
+        SaleH,
+        "
+        SELECT sale_id as `sale_id!`, discount, total, cost, net, date, client_id, rep_cut,
+        status as `status: SaleStatus`, shipping, rep_id, note, backorder_reason, setting_id,
+        rep_payout_id, refunded
+        FROM Sale
+        WHERE status NOT IN ('COMPLETED', 'CANCELLED', 'RETURNED')
+        "
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(sales)
+}
+
+pub async fn get_sale_products_and_client(
+    pool: SqlitePool,
+    sale_id: i64,
+    client_id: i64,
+) -> Result<SC, Errorr> {
+    let sale_products = sqlx::query_as!(SaleProduct,
+                               "
+                               SELECT SaleProduct.product_id, SaleProduct.cost_at_sale, SaleProduct.msrp_at_sale, SaleProduct.qty,
+                               SaleProduct.discount, SaleProduct.discount_percent,
+                               Product.name, Product.units, Product.cost, Product.msrp,
+                               IFNULL((SELECT SUM(SaleReturnProduct.qty) FROM SaleReturnProduct
+                               JOIN SaleReturn ON SaleReturnProduct.return_id = SaleReturn.id
+                               WHERE SaleReturn.sale_id = SaleProduct.sale_id
+                               AND SaleReturnProduct.product_id = SaleProduct.product_id), 0) as `returned!: i64`,
+                               IFNULL((SELECT SUM(SaleReturnProduct.qty) FROM SaleReturnProduct
+                               JOIN SaleReturn ON SaleReturnProduct.return_id = SaleReturn.id
+                               WHERE SaleReturn.sale_id = SaleProduct.sale_id
+                               AND SaleReturnProduct.product_id = SaleProduct.product_id
+                               AND NOT SaleReturnProduct.restocked), 0) as `written_off!: i64`
+                               FROM SaleProduct
+                               JOIN Product ON SaleProduct.product_id = Product.product_id
+                               WHERE SaleProduct.sale_id = ?
+                               ",
+                               sale_id)
+        .fetch_all(&pool)
+        .await?;
+
+    let history = sqlx::query_as!(
+        SaleStatusChange,
+        "
+        SELECT from_status as `from_status: SaleStatus`, to_status as `to_status: SaleStatus`,
+        changed_at
+        FROM SaleStatusChange
+        WHERE sale_id = ?
+        ORDER BY id
+        ",
+        sale_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let returns = get_sale_returns(pool.clone(), sale_id).await?;
+
+    let client = get_client(pool, client_id).await?;
+
+    let r = SC {
+        sale_products,
+        client,
+        history,
+        returns,
+    };
+
+    Ok(r)
+}
+
+pub async fn get_products_and_clients(pool: SqlitePool) -> Result<PCR, Errorr> {
+    let products = get_products(pool.clone()).await?;
+
+    let clients = get_clients(pool.clone()).await?;
+
+    let reps = get_reps(pool).await?;
+
+    let r = PCR {
+        products,
+        clients,
+        reps,
+    };
+
+    Ok(r)
+}
+
+// Replaces the sale's lines. The old lines go back on the shelf and the new
+// ones come off it, so only the difference reaches Product.units. A cancelled
+// or returned sale holds no stock and is left alone. The rep keeps the rate
+// the sale was submitted with unless the rep itself changes, and dates from
+// before validation only have to be valid once they're edited. A sale that was
+// already paid out keeps its rep, changes to its cut go to the next payout.
+pub async fn edit_sale(
+    pool: SqlitePool,
+    mut sale: Sale,
+    mut items: Vec<SaleProductToAdd>,
+    allow_backorder: bool,
+) -> Result<(), Errorr> {
+    check_discounts(&mut sale, &mut items)?;
+    check_backorder(&sale, allow_backorder)?;
+
+    let mut tx = pool.begin().await?;
+
+    let stored = sqlx::query!(
+        "
+        SELECT date, rep_id, rep_percentage, rep_payout_id, setting_id, discount,
+        discount_percent, discount_amount, total, cost, net, shipping, rep_cut
+        FROM Sale
+        WHERE sale_id = ?
+        ",
+        sale.sale_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let stored_lines = sqlx::query!(
+        "
+        SELECT product_id, qty, discount, discount_percent FROM SaleProduct
+        WHERE sale_id = ?
+        ",
+        sale.sale_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if stored.rep_payout_id.is_some() && sale.rep_id != stored.rep_id {
+        return Err(Errorr::InvalidInput(format!(
+            "Sale #{} was already paid out to its rep, the rep can't be changed",
+            sale.sale_id
+        )));
+    }
+    let before = paid_cut(&mut tx, sale.sale_id).await?;
+
+    if sale.date != stored.date {
+        validate_date(&sale.date)?;
+    }
+    if sale.rep_id == stored.rep_id {
+        if let Some(p) = stored.rep_percentage {
+            sale.rep_percentage = p as u8;
+        }
+    }
+
+    let lines_unchanged = stored_lines.len() == items.len()
+        && stored_lines.iter().all(|l| {
+            items.iter().any(|i| {
+                i.product_id == l.product_id
+                    && i.qty == l.qty
+                    && i.discount == l.discount
+                    && i.discount_percent == l.discount_percent
+            })
+        });
+    let unchanged = lines_unchanged
+        && sale.date == stored.date
+        && sale.rep_id == stored.rep_id
+        && sale.discount.unwrap_or(0.00) == stored.discount.unwrap_or(0.00)
+        && sale.discount_percent == stored.discount_percent;
+
+    check_returned_lines(&mut tx, sale.sale_id, &items).await?;
+
+    // Saving without touching anything the totals depend on keeps them as
+    // stored, so sales from before line discounts aren't worked out again.
+    let setting_id = if unchanged {
+        sale.discount_amount = stored.discount_amount;
+        sale.total = stored.total;
+        sale.cost = stored.cost;
+        sale.net = stored.net;
+        sale.shipping = stored.shipping;
+        sale.rep_cut = stored.rep_cut;
+        stored.setting_id
+    } else {
+        // Moving the sale to another day moves it onto that day's rules.
+        let setting = if sale.date != stored.date {
+            setting_on(&mut tx, &sale.date).await?
+        } else {
+            sale_setting(&mut tx, sale.sale_id).await?
+        };
+        compute_totals(&mut sale, &items, &setting);
+        apply_returns(&mut tx, &mut sale).await?;
+        Some(setting.id)
+    };
+
+    let id = sale.sale_id;
+    let discount = sale.discount.unwrap_or(0.00);
+    let discount_percent = sale.discount_percent;
+    let discount_amount = sale.discount_amount;
+    let total = sale.total;
+    let cost = sale.cost;
+    let net = sale.net;
+    let date = sale.date;
+    let client = sale.client_id;
+    let note = sale.note;
+    let rep = sale.rep_id;
+    let rep_percentage = rep.map(|_| sale.rep_percentage);
+    let rep_cut = sale.rep_cut;
+    let shipping = sale.shipping;
+    let backorder_reason = sale.backorder_reason;
+
+    let holds_stock = sale_status(&mut tx, id).await?.holds_stock();
+
+    if holds_stock {
+        restock_sale(&mut tx, id, false).await?;
+    }
+
+    sqlx::query!(
+        "
+        DELETE FROM SaleProduct
+        WHERE sale_id = ?
+        ",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if holds_stock && !allow_backorder {
+        check_stock(&mut tx, &items).await?;
+    }
+
+    insert_lines(&mut tx, id, &items, holds_stock).await?;
+
+    sqlx::query!(
+        "
+        UPDATE Sale
+        SET discount = ?, discount_percent = ?, discount_amount = ?, total = ?, cost = ?,
+        net = ?, date = ?, client_id = ?, note = ?, rep_id = ?, rep_percentage = ?, rep_cut = ?,
+        shipping = ?, backorder_reason = ?, setting_id = ?
+        WHERE sale_id = ?
+        ",
+        discount,
+        discount_percent,
+        discount_amount,
+        total,
+        cost,
+        net,
+        date,
+        client,
+        note,
+        rep,
+        rep_percentage,
+        rep_cut,
+        shipping,
+        backorder_reason,
+        setting_id,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    settle_paid_cut(&mut tx, id, before, None).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Puts the units taken by a sale's lines back on the shelf. Units a return
+// already dealt with are skipped when the sale is leaving stock for good, an
+// edit swaps whole lines and needs every unit back.
+async fn restock_sale(
+    conn: &mut SqliteConnection,
+    sale_id: i64,
+    skip_returned: bool,
+) -> Result<(), Errorr> {
+    let lines = sqlx::query!(
+        "
+        SELECT product_id, qty - CASE WHEN ? THEN IFNULL((SELECT SUM(SaleReturnProduct.qty)
+        FROM SaleReturnProduct
+        JOIN SaleReturn ON SaleReturnProduct.return_id = SaleReturn.id
+        WHERE SaleReturn.sale_id = SaleProduct.sale_id
+        AND SaleReturnProduct.product_id = SaleProduct.product_id), 0) ELSE 0 END AS `qty!: i64`
+        FROM SaleProduct
+        WHERE sale_id = ?
+        ",
+        skip_returned,
+        sale_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for line in &lines {
+        sqlx::query!(
+            "
+            UPDATE Product
+            SET units = units + ?
+            WHERE product_id = ?
+            ",
+            line.qty,
+            line.product_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn sale_status(conn: &mut SqliteConnection, id: i64) -> Result<SaleStatus, Errorr> {
+    let status = sqlx::query_scalar!(
+        "SELECT status as `status: SaleStatus` FROM Sale WHERE sale_id = ?",
+        id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(status)
+}
+
+pub async fn record_status(
+    conn: &mut SqliteConnection,
+    id: i64,
+    from: Option<SaleStatus>,
+    to: SaleStatus,
+) -> Result<(), Errorr> {
+    sqlx::query!(
+        "
+        UPDATE Sale
+        SET status = ?
+        WHERE sale_id = ?
+        ",
+        to,
+        id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "
+        INSERT INTO SaleStatusChange (sale_id, from_status, to_status, changed_at)
+        VALUES (?, ?, ?, datetime('now', 'localtime'))
+        ",
+        id,
+        from,
+        to
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn change_sale_status(pool: SqlitePool, id: i64, to: SaleStatus) -> Result<(), Errorr> {
+    let mut tx = pool.begin().await?;
+
+    let from = sale_status(&mut tx, id).await?;
+
+    // A return records what came back and what was refunded, the status
+    // follows from it.
+    if to == SaleStatus::Returned {
+        return Err(Errorr::InvalidInput(format!(
+            "Record a return to take items back on sale #{}",
+            id
+        )));
+    }
+
+    if !from.next().contains(&to) {
+        return Err(Errorr::InvalidInput(format!(
+            "Sale #{} can't go from {} to {}",
+            id, from, to
+        )));
+    }
+
+    if from.holds_stock() && !to.holds_stock() {
+        restock_sale(&mut tx, id, true).await?;
+    }
+
+    let before = paid_cut(&mut tx, id).await?;
+    record_status(&mut tx, id, Some(from), to).await?;
+    settle_paid_cut(&mut tx, id, before, None).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn delete_sale(pool: SqlitePool, sale: Sale) -> Result<(), Errorr> {
+    let id = sale.sale_id;
+
+    let mut tx = pool.begin().await?;
+
+    // A cancelled or returned sale has already been restocked.
+    if sale_status(&mut tx, id).await?.holds_stock() {
+        restock_sale(&mut tx, id, true).await?;
+    }
+
+    // Whatever the rep was already paid for the sale comes off their next payout.
+    if let Some(cut) = paid_cut(&mut tx, id).await? {
+        add_adjustment(&mut tx, cut.rep_id, id, -cut.earned, None).await?;
+    }
+
+    sqlx::query!(
+        "
+        DELETE FROM Sale
+        WHERE sale_id = ?
+        ",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn add_sale(
+    pool: SqlitePool,
+    mut products_to_add: Vec<SaleProductToAdd>,
+    mut sales: Sale,
+    allow_backorder: bool,
+) -> Result<i64, Errorr> {
+    validate_date(&sales.date)?;
+    check_discounts(&mut sales, &mut products_to_add)?;
+    check_backorder(&sales, allow_backorder)?;
+
+    let mut tx = pool.begin().await?;
+
+    let setting = setting_on(&mut tx, &sales.date).await?;
+    compute_totals(&mut sales, &products_to_add, &setting);
+    let setting_id = setting.id;
+
+    let discount = sales.discount;
+    let discount_percent = sales.discount_percent;
+    let discount_amount = sales.discount_amount;
+    let total = sales.total;
+    let cost = sales.cost;
+    let net = sales.net;
+    let date = sales.date;
+    let client = sales.client_id;
+    let note = sales.note;
+    let rep = sales.rep_id;
+    let rep_percentage = rep.map(|_| sales.rep_percentage);
+    let rep_cut = sales.rep_cut;
+    let shipping = sales.shipping;
+    let backorder_reason = sales.backorder_reason;
+
+    if !allow_backorder {
+        check_stock(&mut tx, &products_to_add).await?;
+    }
+
+    let sale = sqlx::query!(
+        "
+        INSERT INTO Sale ( discount, discount_percent, discount_amount, total, cost, net, date, client_id, note, rep_id, rep_percentage, rep_cut, shipping, backorder_reason, setting_id )
+        VALUES (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)
+        ",
+        discount,
+        discount_percent,
+        discount_amount,
+        total,
+        cost,
+        net,
+        date,
+        client,
+        note,
+        rep,
+        rep_percentage,
+        rep_cut,
+        shipping,
+        backorder_reason,
+        setting_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let sale_id = sale.last_insert_rowid();
+
+    insert_lines(&mut tx, sale_id, &products_to_add, true).await?;
+
+    record_status(&mut tx, sale_id, None, SaleStatus::Draft).await?;
+
+    tx.commit().await?;
+
+    Ok(sale_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(qty: i64, msrp: f64, cost: f64, discount: &str, percent: bool) -> SaleProductToAdd {
+        SaleProductToAdd {
+            product_id: 1,
+            name: String::from("Widget"),
+            qty,
+            msrp,
+            cost,
+            units: 100,
+            discount_input: discount.to_string(),
+            discount_percent: percent,
+            ..Default::default()
+        }
+    }
+
+    fn setting() -> Setting {
+        Setting {
+            shipping: 15.00,
+            free_shipping_threshold: 100.00,
+            carrier_cost: 10.00,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sale_status_moves_forward_only() {
+        use SaleStatus::*;
+
+        assert_eq!(Draft.next(), &[Confirmed, Cancelled]);
+        assert!(Paid.next().contains(&Cancelled));
+        assert!(!Shipped.next().contains(&Cancelled));
+        assert!(Shipped.next().contains(&Paid));
+        assert_eq!(Completed.next(), &[Returned]);
+        assert!(Cancelled.next().is_empty());
+        assert!(Returned.next().is_empty());
+        for status in [Draft, Confirmed, Paid, Shipped, Completed] {
+            assert!(!status.next().contains(&status));
+        }
+    }
+
+    #[test]
+    fn only_cancelled_and_returned_sales_give_up_their_stock() {
+        use SaleStatus::*;
+
+        for status in [Draft, Confirmed, Paid, Shipped, Completed] {
+            assert!(status.holds_stock());
+        }
+        assert!(!Cancelled.holds_stock());
+        assert!(!Returned.holds_stock());
+    }
+
+    #[test]
+    fn a_refund_on_a_paid_sale_leaves_nothing_owing() {
+        let sale = Sale {
+            total: 100.00,
+            paid: 100.00,
+            refunded: 40.00,
+            ..Default::default()
+        };
+
+        assert_eq!(sale.balance(), 0.00);
+    }
+
+    #[test]
+    fn a_refund_on_a_part_paid_sale_leaves_the_rest_owing() {
+        let sale = Sale {
+            total: 100.00,
+            paid: 60.00,
+            refunded: 20.00,
+            ..Default::default()
+        };
+
+        assert_eq!(sale.balance(), 40.00);
+    }
+
+    #[test]
+    fn discount_off_takes_a_percentage_or_a_fixed_amount() {
+        assert_eq!(discount_off(200.00, 10.00, true), 20.00);
+        assert_eq!(discount_off(200.00, 10.00, false), 10.00);
+    }
+
+    #[test]
+    fn discount_off_never_goes_below_zero() {
+        assert_eq!(discount_off(50.00, 80.00, false), 50.00);
+        assert_eq!(discount_off(50.00, 150.00, true), 50.00);
+        assert_eq!(discount_off(-5.00, 10.00, false), 0.00);
+    }
+
+    #[test]
+    fn check_discounts_reads_the_typed_line_discounts() {
+        let mut sale = Sale {
+            discount_input: String::from(" 5 "),
+            ..Default::default()
+        };
+        let mut items = vec![
+            line(1, 10.00, 5.00, " 2.5 ", false),
+            line(2, 10.00, 5.00, "", true),
+        ];
+
+        check_discounts(&mut sale, &mut items).unwrap();
+
+        assert_eq!(sale.discount, Some(5.00));
+        assert_eq!(items[0].discount, 2.50);
+        assert_eq!(items[1].discount, 0.00);
+    }
+
+    #[test]
+    fn check_discounts_rejects_bad_discounts() {
+        for (input, percent) in [("abc", false), ("-1", false), ("101", true)] {
+            let mut items = vec![line(1, 10.00, 5.00, input, percent)];
+            assert!(matches!(
+                check_discounts(&mut Sale::default(), &mut items),
+                Err(Errorr::InvalidInput(_))
+            ));
+
+            let mut sale = Sale {
+                discount_input: String::from(input),
+                discount_percent: percent,
+                ..Default::default()
+            };
+            assert!(matches!(
+                check_discounts(&mut sale, &mut []),
+                Err(Errorr::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn compute_totals_takes_line_discounts_before_the_sale_discount() {
+        let mut sale = Sale {
+            discount_input: String::from("10"),
+            ..Default::default()
+        };
+        let mut items = vec![
+            line(2, 50.00, 20.00, "10", true),
+            line(1, 30.00, 10.00, "", false),
+        ];
+        check_discounts(&mut sale, &mut items).unwrap();
+
+        compute_totals(&mut sale, &items, &setting());
+
+        // 90 + 30 for the lines, less 10 off the sale, clears the threshold.
+        assert_eq!(sale.discount_amount, 20.00);
+        assert_eq!(sale.shipping, 0.00);
+        assert_eq!(sale.total, 110.00);
+        assert_eq!(sale.cost, 60.00);
+        assert_eq!(sale.net, 50.00);
+        assert_eq!(sale.rep_cut, None);
+    }
+
+    #[test]
+    fn compute_totals_charges_shipping_under_the_threshold() {
+        let mut sale = Sale {
+            rep_id: Some(1),
+            rep_percentage: 10,
+            ..Default::default()
+        };
+        let items = vec![line(1, 50.00, 20.00, "", false)];
+
+        compute_totals(&mut sale, &items, &setting());
+
+        // The rep's cut goes by the products, not the shipping.
+        assert_eq!(sale.shipping, 15.00);
+        assert_eq!(sale.total, 65.00);
+        assert_eq!(sale.cost, 30.00);
+        assert_eq!(sale.rep_cut, Some(5.00));
+        assert_eq!(sale.net, 30.00);
+    }
+}
@@ -0,0 +1,133 @@
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{error::Errorr, repo::validate_date};
+
+#[derive(Default, Clone, Debug)]
+pub struct Setting {
+    pub id: i64,
+    pub effective_date: String,
+    pub shipping: f64,
+    pub free_shipping_threshold: f64,
+    pub carrier_cost: f64,
+    pub payment_terms: i64,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct SettingToAdd {
+    pub effective_date: String,
+    pub shipping: String,
+    pub free_shipping_threshold: String,
+    pub carrier_cost: String,
+    pub payment_terms: String,
+}
+
+fn parse_amount(label: &str, input: &str) -> Result<f64, Errorr> {
+    input
+        .parse::<f64>()
+        .ok()
+        .filter(|a| a.is_finite() && *a >= 0.00)
+        .ok_or(Errorr::InvalidInput(format!(
+            "\"{}\" is not a valid {}",
+            input, label
+        )))
+}
+
+pub async fn get_settings(pool: SqlitePool) -> Result<Vec<Setting>, Errorr> {
+    let settings = sqlx::query_as!(
+        Setting,
+        "
+        SELECT * FROM Setting
+        ORDER BY effective_date DESC, id DESC
+        "
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(settings)
+}
+
+// The newest version that had taken effect by the date, so a backdated sale
+// gets the rules of its own day. Dates that can't be read go by today.
+pub async fn setting_on(conn: &mut SqliteConnection, date: &str) -> Result<Setting, Errorr> {
+    let setting = sqlx::query_as!(
+        Setting,
+        "
+        SELECT * FROM Setting
+        WHERE effective_date <= IFNULL(date(?), date('now', 'localtime'))
+        ORDER BY effective_date DESC, id DESC
+        LIMIT 1
+        ",
+        date
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    setting.ok_or(Errorr::ConfigMissing(format!(
+        "No shipping settings were in effect on {}",
+        date
+    )))
+}
+
+// Rules a sale was created under, sales without a version fall back to the
+// ones in effect on their date.
+pub async fn sale_setting(conn: &mut SqliteConnection, sale_id: i64) -> Result<Setting, Errorr> {
+    let setting = sqlx::query_as!(
+        Setting,
+        "
+        SELECT Setting.* FROM Setting
+        JOIN Sale ON Sale.setting_id = Setting.id
+        WHERE Sale.sale_id = ?
+        ",
+        sale_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    match setting {
+        Some(s) => Ok(s),
+        None => {
+            let date = sqlx::query_scalar!("SELECT date FROM Sale WHERE sale_id = ?", sale_id)
+                .fetch_one(&mut *conn)
+                .await?;
+            setting_on(conn, &date).await
+        }
+    }
+}
+
+// Versions are never edited in place, a change is a new row with the date it
+// starts applying from.
+pub async fn add_setting(pool: SqlitePool, setting: SettingToAdd) -> Result<i64, Errorr> {
+    validate_date(&setting.effective_date)?;
+
+    let effective_date = setting.effective_date;
+    let shipping = parse_amount("shipping charge", &setting.shipping)?;
+    let free_shipping_threshold =
+        parse_amount("free shipping threshold", &setting.free_shipping_threshold)?;
+    let carrier_cost = parse_amount("carrier cost", &setting.carrier_cost)?;
+    let payment_terms = setting
+        .payment_terms
+        .parse::<i64>()
+        .ok()
+        .filter(|d| *d >= 0)
+        .ok_or(Errorr::InvalidInput(format!(
+            "\"{}\" is not a valid number of days",
+            setting.payment_terms
+        )))?;
+
+    let r = sqlx::query!(
+        "
+        INSERT INTO Setting (effective_date, shipping, free_shipping_threshold, carrier_cost,
+        payment_terms)
+        VALUES (?,?,?,?,?)
+        ",
+        effective_date,
+        shipping,
+        free_shipping_threshold,
+        carrier_cost,
+        payment_terms
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(r.last_insert_rowid())
+}
@@ -0,0 +1,154 @@
+use iced::{
+    widget::{Button, Column, Container, Row, Scrollable, Text},
+    Alignment, Element, Length,
+};
+
+use crate::{
+    components::{
+        bold_text, layout, table_column, table_header, table_row_style, table_style,
+        text_input_column, CustomMainButtonStyle,
+    },
+    repo::report::{ReportFilter, ReportGrouping, ReportRow},
+    AppMessage,
+};
+
+#[derive(Default, Clone)]
+pub struct ReportsState {
+    pub filter: ReportFilter,
+    pub rows: Vec<ReportRow>,
+}
+
+#[derive(Clone, Debug)]
+pub enum ReportsMessage {
+    FromInput(String),
+    ToInput(String),
+    Grouping(ReportGrouping),
+    Run,
+}
+
+fn report_row(row: &ReportRow) -> Row<'static, AppMessage> {
+    Row::new()
+        .padding(10)
+        .push(table_column(&row.label))
+        .push(table_column(&row.sales.to_string()))
+        .push(table_column(&format!("${:.2}", row.revenue)))
+        .push(table_column(&format!("${:.2}", row.returns)))
+        .push(table_column(&format!("${:.2}", row.cogs)))
+        .push(table_column(&format!("${:.2}", row.shipping)))
+        .push(table_column(&format!("${:.2}", row.commission)))
+        .push(table_column(&format!("${:.2}", row.net)))
+        .push(table_column(&format!("{:.1}%", row.margin())))
+}
+
+impl ReportsState {
+    pub fn update(&mut self, message: ReportsMessage) {
+        match message {
+            ReportsMessage::FromInput(s) => {
+                self.filter.from = s;
+            }
+            ReportsMessage::ToInput(s) => {
+                self.filter.to = s;
+            }
+            ReportsMessage::Grouping(g) => {
+                self.filter.grouping = g;
+            }
+            ReportsMessage::Run => {}
+        }
+    }
+
+    fn totals(&self) -> ReportRow {
+        let mut totals = ReportRow {
+            label: String::from("Total"),
+            ..Default::default()
+        };
+
+        for row in &self.rows {
+            totals.sales += row.sales;
+            totals.revenue += row.revenue;
+            totals.returns += row.returns;
+            totals.cogs += row.cogs;
+            totals.shipping += row.shipping;
+            totals.commission += row.commission;
+            totals.net += row.net;
+        }
+
+        totals
+    }
+
+    pub fn view(&self) -> Element<'_, AppMessage> {
+        layout(
+            Column::new()
+                .width(Length::Fill)
+                .padding([12, 0, 0, 12])
+                .spacing(12)
+                .align_items(Alignment::Center)
+                .push(Text::new("Reports".to_string()).size(24))
+                .push(
+                    Row::new()
+                        .width(600)
+                        .spacing(12)
+                        .push(text_input_column(
+                            "From (YYYY-MM-DD)",
+                            &self.filter.from,
+                            |input| AppMessage::Reports(ReportsMessage::FromInput(input)),
+                            Some(AppMessage::Reports(ReportsMessage::Run)),
+                        ))
+                        .push(text_input_column(
+                            "To (YYYY-MM-DD)",
+                            &self.filter.to,
+                            |input| AppMessage::Reports(ReportsMessage::ToInput(input)),
+                            Some(AppMessage::Reports(ReportsMessage::Run)),
+                        )),
+                )
+                .push(
+                    Row::new()
+                        .spacing(8)
+                        .align_items(Alignment::Center)
+                        .push(bold_text("Group by"))
+                        .extend(ReportGrouping::ALL.iter().map(|g| {
+                            let button = Button::new(Text::new(g.to_string()))
+                                .on_press(AppMessage::Reports(ReportsMessage::Grouping(*g)));
+                            if *g == self.filter.grouping {
+                                button.style(CustomMainButtonStyle).into()
+                            } else {
+                                button.into()
+                            }
+                        }))
+                        .push(
+                            Button::new("Run")
+                                .on_press(AppMessage::Reports(ReportsMessage::Run))
+                                .style(CustomMainButtonStyle),
+                        ),
+                )
+                .push(Text::new(
+                    "Returns are booked to the day they came back, not to the original sale.",
+                ))
+                .push(
+                    Container::new(
+                        table_header(&[
+                            &self.filter.grouping.to_string(),
+                            "Sales",
+                            "Revenue",
+                            "Returns",
+                            "COGS",
+                            "Shipping",
+                            "Commission",
+                            "Net",
+                            "Margin",
+                        ])
+                        .push(Scrollable::new(Column::new().extend(self.rows.iter().map(
+                            |row| {
+                                Container::new(report_row(row))
+                                    .style(table_row_style())
+                                    .into()
+                            },
+                        ))))
+                        .push(Container::new(report_row(&self.totals())).style(table_row_style())),
+                    )
+                    .style(table_style()),
+                )
+                .into(),
+        )
+        .into()
+    }
+}
@@ -1,73 +1,36 @@
 use clipboard::{ClipboardContext, ClipboardProvider};
-use std::env;
 
 use iced::{
     alignment::Horizontal,
     widget::{
         scrollable::{Direction, Properties},
-        Button, Column, Container, Row, Scrollable, Text, TextInput,
+        Button, Checkbox, Column, Container, Row, Scrollable, Text, TextInput,
     },
     Alignment, Element, Length,
 };
 
-use sqlx::SqlitePool;
-
 use crate::{
-    clients::{get_client, get_clients, Client},
     components::{
-        add_button, bold_text, card_style, close_button, close_edit_row, layout, table_column, table_header, table_row_style, table_style, text_input_column, CustomButtonStyle, CustomMainButtonStyle
+        add_button, bold_text, card_style, close_button, close_edit_row, export_row, layout, shortage_view, table_column, table_header, table_row_style, table_style, text_input_column, CustomButtonStyle, CustomMainButtonStyle
     },
     manufacture::select_header,
-    product::{get_products, Product},
-    rep::{get_reps, Rep},
+    purchase::validate_input,
+    repo::{
+        client::Client,
+        invoice::Document,
+        payment::{Payment, PaymentToAdd},
+        rep::Rep,
+        returns::{ReturnLine, ReturnToAdd, SaleReturn},
+        sale::{sale_shortages, Sale, SaleProduct, SaleProductToAdd, SaleStatus, SaleStatusChange},
+    },
     AppMessage,
 };
 
-use crate::error::Errorr;
-
-#[derive(Default, Clone, Debug)]
-pub struct SaleProduct {
-    pub name: String,
-    pub qty: i64,
-    pub units: i64,
-    pub msrp: f64,
-    pub cost: f64,
-    pub cost_at_sale: f64,
-    pub msrp_at_sale: f64,
-}
-
-#[derive(Clone, Default, Debug)]
-pub struct SaleProductToAdd {
-    pub product_id: i64,
-    pub name: String,
-    pub qty: i64,
-    pub msrp: f64,
-    pub cost: f64,
-    pub units: i64,
-}
-
-#[derive(Debug, Clone, Default)]
-pub struct Sale {
-    pub sale_id: i64,
-    pub discount: Option<f64>,
-    pub total: f64,
-    pub cost: f64,
-    pub net: f64,
-    pub date: String,
-    pub client_id: i64,
-    pub client_name: String,
-    pub note: Option<String>,
-    pub rep_id: Option<i64>,
-    pub rep_name: String,
-    pub rep_percentage: u8,
-    pub rep_cut: Option<f64>,
-    pub status: String,
-    pub shipping: f64,
-}
-
 #[derive(Default, Clone)]
 pub struct SalesState {
     pub sales: Vec<Sale>,
+    pub export_path: String,
+    pub document_dir: String,
     pub add_sales: Sale,
     pub sales_products: Vec<SaleProduct>,
     pub clients: Vec<Client>,
@@ -75,6 +38,12 @@ pub struct SalesState {
     pub edit_sale: bool,
     pub sale_to_edit: Sale,
     pub sale_products_to_view: Vec<SaleProduct>,
+    pub status_history: Vec<SaleStatusChange>,
+    pub payments: Vec<Payment>,
+    pub payment_to_add: PaymentToAdd,
+    pub returns: Vec<SaleReturn>,
+    pub add_return: bool,
+    pub return_to_add: ReturnToAdd,
     create_client: bool,
     pub client_to_create: Client,
     pub client_to_view: Client,
@@ -91,6 +60,10 @@ pub struct SalesState {
     client_query: String,
     pub filtered_reps: Vec<Rep>,
     rep_query: String,
+    allow_backorder: bool,
+    pub override_shortage: bool,
+    confirm_delete: bool,
+    confirm_status: Option<SaleStatus>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -109,6 +82,9 @@ pub enum SaleMessage {
     RepName(String),
     RepPercentage(String),
     DiscountInput(String, bool),
+    DiscountPercent(bool, bool),
+    LineDiscount(i64, String),
+    LineDiscountPercent(i64, bool),
     DateInput(String, bool),
     NoteInput(String, bool),
     EditClient(i64),
@@ -120,133 +96,29 @@ pub enum SaleMessage {
     ClientQuery(String),
     RepQuery(String),
     CopyClientInfo,
-    Fulfill,
+    SetStatus(SaleStatus),
     CloseSale,
-}
-
-pub async fn get_sales() -> Result<Vec<Sale>, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let sales = sqlx::query_as!(Sale,
-                                "SELECT Sale.sale_id, discount, total, Sale.cost, Sale.client_id, net, date, note, rep_id, shipping, status, rep_cut,
-                                Client.name as client_name,
-                                Rep.name as rep_name, Rep.percentage as `rep_percentage: u8`
-                                FROM Sale
-                                JOIN Client ON Sale.client_id = Client.client_id
-                                JOIN Rep ON Sale.rep_id = Rep.id
-                                "
-                               )
-        .fetch_all(&pool)
-        .await?;
-
-    Ok(sales)
-}
-
-#[derive(Clone, Debug)]
-pub struct SC {
-    pub sale_products: Vec<SaleProduct>,
-    pub client: Client,
-}
-
-pub async fn get_sale_products_and_client(sale_id: i64, client_id: i64) -> Result<SC, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let sale_products = sqlx::query_as!(SaleProduct,
-                               "
-                               SELECT SaleProduct.cost_at_sale, SaleProduct.msrp_at_sale, SaleProduct.qty,
-                               Product.name, Product.units, Product.cost, Product.msrp
-                               FROM SaleProduct
-                               JOIN Product ON SaleProduct.product_id = Product.product_id
-                               WHERE SaleProduct.sale_id = ?
-                               ",
-                               sale_id)
-        .fetch_all(&pool)
-        .await?;
-
-    let client = get_client(client_id).await?;
-
-    let r = SC {
-        sale_products,
-        client,
-    };
-
-    Ok(r)
-}
-
-#[derive(Clone, Debug)]
-pub struct PCR {
-    pub products: Vec<Product>,
-    pub clients: Vec<Client>,
-    pub reps: Vec<Rep>,
-}
-
-pub async fn get_products_and_clients() -> Result<PCR, Errorr> {
-    let products = get_products().await?;
-
-    let clients = get_clients().await?;
-
-    let reps = get_reps().await?;
-
-    let r = PCR {
-        products,
-        clients,
-        reps,
-    };
-
-    Ok(r)
-}
-
-pub async fn add_client_set(client: Client) -> Result<i64, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let name = client.name;
-    let address = client.address;
-    let email = client.email;
-
-    let c = sqlx::query!(
-        "
-        INSERT INTO Client (name, address, email)
-        VALUES (?,?,?)
-        ",
-        name,
-        address,
-        email,
-    )
-    .execute(&pool)
-    .await?;
-
-    Ok(c.last_insert_rowid())
-}
-
-#[derive(Clone, Debug)]
-pub struct R {
-    pub id: i64,
-    pub name: String,
-}
-
-pub async fn add_rep_set(rep: Rep) -> Result<R, Errorr> {
-    let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-    let name = rep.name;
-    let percentage = rep.percentage;
-
-    let c = sqlx::query!(
-        "
-        INSERT INTO Rep (name, percentage)
-        VALUES (?,?)
-        ",
-        name,
-        percentage
-    )
-    .execute(&pool)
-    .await?;
-
-    let r = R {
-        id: c.last_insert_rowid(),
-        name,
-    };
-
-    Ok(r)
+    AllowBackorder(bool),
+    BackorderReason(String),
+    ConfirmDelete,
+    ConfirmStatus(SaleStatus),
+    AbortConfirm,
+    ExportPath(String),
+    Export,
+    DocumentDir(String),
+    WriteDocument(Document),
+    PaymentDate(String),
+    PaymentAmount(String),
+    PaymentMethod(String),
+    PaymentReference(String),
+    RecordPayment,
+    ShowAddReturn,
+    ReturnQty(i64, String),
+    ReturnRestock(i64, bool),
+    ReturnDate(String),
+    ReturnRefund(String),
+    ReturnNote(String),
+    SubmitReturn,
 }
 
 fn item_view_row(label: &str, value: String) -> Row<'static, AppMessage> {
@@ -261,9 +133,17 @@ fn item_view_row(label: &str, value: String) -> Row<'static, AppMessage> {
         )
 }
 
+fn discount_label(discount: f64, percent: bool) -> String {
+    if percent {
+        format!("{}%", discount)
+    } else {
+        format!("${:.2}", discount)
+    }
+}
+
 fn item_view(item: &SaleProduct) -> Container<'static, AppMessage> {
-    let total = item.msrp * item.qty as f64;
-    let net = total - item.cost * item.qty as f64;
+    let total = item.line_total();
+    let net = total - item.cost_at_sale * item.qty as f64;
 
     Container::new(
         Column::new()
@@ -271,6 +151,14 @@ fn item_view(item: &SaleProduct) -> Container<'static, AppMessage> {
             .push(item_view_row("Quantity: ", item.qty.to_string()))
             .push(item_view_row("Cost: ", format!("${:.2}", item.cost)))
             .push(item_view_row("MSRP: ", format!("${:.2}", item.msrp)))
+            .push_maybe(if item.discount > 0.00 {
+                Some(item_view_row(
+                    "Discount: ",
+                    discount_label(item.discount, item.discount_percent),
+                ))
+            } else {
+                None
+            })
             .push(item_view_row("Net: ", format!("${:.2}", net)))
             .push(item_view_row("Total: ", format!("${:.2}", total))),
     )
@@ -299,148 +187,145 @@ fn client_view(client: &Client) -> Container<'static, AppMessage> {
     )
 }
 
-impl SalesState {
-    pub async fn edit_sale(sale: Sale) -> Result<(), Errorr> {
-        let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-        let id = sale.sale_id;
-        let discount = sale.discount.unwrap_or(0.00);
-        let date = sale.date;
-        let client = sale.client_id;
-        let note = sale.note;
-
-        sqlx::query!(
-            "
-            UPDATE Sale
-            SET discount = ?, date = ?, client_id = ?, note = ?
-            WHERE sale_id = ?
-            ",
-            discount,
-            date,
-            client,
-            note,
-            id
-        )
-        .execute(&pool)
-        .await?;
-
-        Ok(())
+fn status_label(status: SaleStatus) -> &'static str {
+    match status {
+        SaleStatus::Draft => "Back to Draft",
+        SaleStatus::Confirmed => "Confirm",
+        SaleStatus::Paid => "Mark Paid",
+        SaleStatus::Shipped => "Mark Shipped",
+        SaleStatus::Completed => "Complete",
+        SaleStatus::Cancelled => "Cancel Sale",
+        SaleStatus::Returned => "Mark Returned",
     }
+}
 
-    pub async fn fulfill_sale(id: i64) -> Result<(), Errorr> {
-        let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-        sqlx::query!(
-            "
-            UPDATE Sale
-            SET status = ?
-            WHERE sale_id = ?
-            ",
-            "COMPLETED",
-            id
-        )
-        .execute(&pool)
-        .await?;
-
-        Ok(())
+// Statuses that put stock back ask for confirmation first.
+fn status_button(status: SaleStatus) -> Button<'static, AppMessage> {
+    if status.holds_stock() {
+        Button::new(status_label(status))
+            .on_press(AppMessage::Sale(SaleMessage::SetStatus(status)))
+            .style(CustomMainButtonStyle)
+    } else {
+        Button::new(status_label(status))
+            .on_press(AppMessage::Sale(SaleMessage::ConfirmStatus(status)))
+            .style(iced::theme::Button::Destructive)
     }
+}
 
-    pub async fn delete_sale(sale: Sale) -> Result<(), Errorr> {
-        let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-
-        let id = sale.sale_id;
-
-        sqlx::query!(
-            "
-            DELETE FROM Sale
-            WHERE sale_id = ?
-            ",
-            id
-        )
-        .execute(&pool)
-        .await?;
-
-        Ok(())
+impl SalesState {
+    // The sale the product, client and rep selectors are working on.
+    fn sale(&self) -> &Sale {
+        if self.edit_sale {
+            &self.sale_to_edit
+        } else {
+            &self.add_sales
+        }
     }
 
-    pub async fn add_sales(
-        i: Vec<SaleProductToAdd>,
-        j: Vec<SaleProductToAdd>,
-        sales: Sale,
-    ) -> Result<(), Errorr> {
-        let pool = SqlitePool::connect(&env::var("DATABASE_URL")?).await?;
-        let discount = sales.discount;
-        let total = sales.total;
-        let cost = sales.cost;
-        let net = sales.net;
-        let date = sales.date;
-        let client = sales.client_id;
-        let note = sales.note;
-        let rep = sales.rep_id;
-        let rep_cut = sales.rep_cut;
-        let shipping = sales.shipping;
-
-        let sale = sqlx::query!(
-            "
-            INSERT INTO Sale ( discount, total, cost, net, date, client_id, note, rep_id, rep_cut, shipping )
-            VALUES (?,?,?,?,?,?,?,?,?,?)
-            ",
-            discount,
-            total,
-            cost,
-            net,
-            date,
-            client,
-            note,
-            rep,
-            rep_cut,
-            shipping
-            )
-            .execute(&pool)
-            .await?;
-
-        let sale_id = sale.last_insert_rowid();
-
-        for item in &j {
-            sqlx::query!(
-                "
-                INSERT INTO SaleProduct ( sale_id, qty, product_id, cost_at_sale, msrp_at_sale )
-                VALUES (?,?,?,?,?)
-                ",
-                sale_id,
-                item.qty,
-                item.product_id,
-                item.cost,
-                item.msrp
-            )
-            .execute(&pool)
-            .await?;
-
-            let units = i
-                .iter()
-                .find(|i| i.product_id == item.product_id)
-                .unwrap()
-                .units
-                - item.qty;
-
-            sqlx::query!(
-                "
-                    UPDATE Product
-                    SET units = ?
-                    WHERE product_id = ?
-                    ",
-                units,
-                item.product_id
-            )
-            .execute(&pool)
-            .await?;
+    pub fn sale_mut(&mut self) -> &mut Sale {
+        if self.edit_sale {
+            &mut self.sale_to_edit
+        } else {
+            &mut self.add_sales
         }
-
-        Ok(())
     }
 
     pub fn update(&mut self, message: SaleMessage) {
         match message {
+            SaleMessage::ExportPath(p) => {
+                self.export_path = p;
+            }
+            SaleMessage::Export => {}
+            SaleMessage::DocumentDir(d) => {
+                self.document_dir = d;
+            }
+            SaleMessage::WriteDocument(_) => {}
+            SaleMessage::PaymentDate(d) => {
+                self.payment_to_add.date = d;
+            }
+            SaleMessage::PaymentAmount(a) => {
+                if validate_input(&a) {
+                    self.payment_to_add.amount = a;
+                }
+            }
+            SaleMessage::PaymentMethod(m) => {
+                self.payment_to_add.method = m;
+            }
+            SaleMessage::PaymentReference(r) => {
+                self.payment_to_add.reference = r;
+            }
+            SaleMessage::RecordPayment => {}
+            SaleMessage::ShowAddReturn => {
+                self.add_return = !self.add_return;
+                self.return_to_add = ReturnToAdd {
+                    lines: self
+                        .sale_products_to_view
+                        .iter()
+                        .filter(|item| item.qty > item.returned)
+                        .map(|item| ReturnLine {
+                            product_id: item.product_id,
+                            name: item.name.clone(),
+                            qty: 0,
+                            restock: true,
+                        })
+                        .collect(),
+                    ..Default::default()
+                };
+            }
+            SaleMessage::ReturnQty(id, qty) => {
+                let left = self
+                    .sale_products_to_view
+                    .iter()
+                    .find(|item| item.product_id == id)
+                    .map(|item| item.qty - item.returned)
+                    .unwrap_or(0);
+                if let Some(line) = self.return_to_add.lines.iter_mut().find(|l| l.product_id == id) {
+                    line.qty = qty.parse::<i64>().unwrap_or(0).clamp(0, left);
+                }
+
+                // Suggest refunding what the units sold for after discounts, it
+                // can still be changed.
+                let lines_total: f64 = self
+                    .sale_products_to_view
+                    .iter()
+                    .map(|item| item.line_total())
+                    .sum();
+                let product_total = self.sale_to_view.total - self.sale_to_view.shipping;
+                let refund: f64 = self
+                    .return_to_add
+                    .lines
+                    .iter()
+                    .filter_map(|l| {
+                        self.sale_products_to_view
+                            .iter()
+                            .find(|item| item.product_id == l.product_id && item.qty > 0)
+                            .map(|item| item.line_total() / item.qty as f64 * l.qty as f64)
+                    })
+                    .sum();
+                let refund = if lines_total > 0.00 {
+                    refund * product_total / lines_total
+                } else {
+                    refund
+                };
+                self.return_to_add.refund = format!("{:.2}", refund);
+            }
+            SaleMessage::ReturnRestock(id, restock) => {
+                if let Some(line) = self.return_to_add.lines.iter_mut().find(|l| l.product_id == id) {
+                    line.restock = restock;
+                }
+            }
+            SaleMessage::ReturnDate(d) => {
+                self.return_to_add.date = d;
+            }
+            SaleMessage::ReturnRefund(r) => {
+                if validate_input(&r) {
+                    self.return_to_add.refund = r;
+                }
+            }
+            SaleMessage::ReturnNote(n) => {
+                self.return_to_add.note = n;
+            }
+            SaleMessage::SubmitReturn => {}
             SaleMessage::ProductQtyChanged(qty, id, cost, msrp) => {
                 if let Some(i) = self
                     .filtered_products
@@ -475,16 +360,31 @@ impl SalesState {
                 }
             }
             SaleMessage::DiscountInput(d, is_edit) => {
+                // Kept as typed like the line discounts and read on submit.
                 if is_edit {
-                    self.sale_to_edit.discount = Some(d.parse::<f64>().unwrap_or(0.00))
+                    self.sale_to_edit.discount_input = d;
                 } else {
-                    let discount = d.parse::<f64>().unwrap_or(0.00);
-
-                    if discount == 0.00 {
-                        self.add_sales.discount = None;
-                    } else {
-                        self.add_sales.discount = Some(discount);
-                    }
+                    self.add_sales.discount_input = d;
+                }
+            }
+            SaleMessage::DiscountPercent(b, is_edit) => {
+                if is_edit {
+                    self.sale_to_edit.discount_percent = b;
+                } else {
+                    self.add_sales.discount_percent = b;
+                }
+            }
+            SaleMessage::LineDiscount(id, d) => {
+                if let Some(p) = self.products_to_add.iter_mut().find(|p| p.product_id == id) {
+                    // The text is kept as typed and read on submit, the
+                    // number only keeps the running totals up to date.
+                    p.discount = d.parse::<f64>().unwrap_or(0.00);
+                    p.discount_input = d;
+                }
+            }
+            SaleMessage::LineDiscountPercent(id, b) => {
+                if let Some(p) = self.products_to_add.iter_mut().find(|p| p.product_id == id) {
+                    p.discount_percent = b;
                 }
             }
             SaleMessage::DateInput(d, is_edit) => {
@@ -495,8 +395,9 @@ impl SalesState {
                 }
             }
             SaleMessage::AddClient(cid, cname) => {
-                self.add_sales.client_id = cid;
-                self.add_sales.client_name = cname;
+                let sale = self.sale_mut();
+                sale.client_id = cid;
+                sale.client_name = cname;
             }
             SaleMessage::CreateClient => {
                 if self.create_client {
@@ -506,7 +407,7 @@ impl SalesState {
                 }
             }
             SaleMessage::CreateClientSubmit => {
-                self.add_sales.client_id = self.client_to_create.client_id;
+                self.sale_mut().client_id = self.client_to_create.client_id;
                 self.create_client = false;
             }
             SaleMessage::ClientName(s) => {
@@ -522,9 +423,10 @@ impl SalesState {
                 self.client_to_view.client_id = i;
             }
             SaleMessage::AddRep(cid, cname, p) => {
-                self.add_sales.rep_id = Some(cid);
-                self.add_sales.rep_name = cname;
-                self.add_sales.rep_percentage = p;
+                let sale = self.sale_mut();
+                sale.rep_id = Some(cid);
+                sale.rep_name = cname;
+                sale.rep_percentage = p;
             }
             SaleMessage::CreateRep => {
                 if self.create_rep {
@@ -534,7 +436,7 @@ impl SalesState {
                 }
             }
             SaleMessage::CreateRepSubmit => {
-                self.add_sales.rep_id = Some(self.rep_to_create.id);
+                self.sale_mut().rep_id = Some(self.rep_to_create.id);
                 self.create_rep = false;
             }
             SaleMessage::RepName(s) => {
@@ -545,19 +447,21 @@ impl SalesState {
             }
             SaleMessage::NoteInput(n, is_edit) => {
                 if is_edit {
-                    if n.chars().count() == 0 {
-                        self.add_sales.note = None;
-                    } else {
-                        self.add_sales.note = Some(n);
-                    }
-                } else {
                     self.sale_to_edit.note = Some(n);
+                } else if n.chars().count() == 0 {
+                    self.add_sales.note = None;
+                } else {
+                    self.add_sales.note = Some(n);
                 }
             }
             SaleMessage::ShowAddProducts => {
                 if self.add_sale {
                     self.add_sale = false;
                 } else {
+                    if self.edit_sale {
+                        self.edit_sale = false;
+                        self.products_to_add = Vec::new();
+                    }
                     self.view_sale = false;
                     self.add_sale = true;
                 }
@@ -566,41 +470,50 @@ impl SalesState {
                 self.sale_to_edit.client_id = c;
             }
             SaleMessage::Delete => {
+                self.confirm_delete = false;
                 self.edit_sale = false;
                 self.view_sale = false;
             }
+            SaleMessage::ConfirmDelete => {
+                self.confirm_delete = true;
+            }
+            SaleMessage::ConfirmStatus(s) => {
+                self.confirm_status = Some(s);
+            }
+            SaleMessage::AbortConfirm => {
+                self.confirm_delete = false;
+                self.confirm_status = None;
+            }
+            SaleMessage::SetStatus(_) => {
+                self.confirm_status = None;
+            }
+            // Stock is only let go short when the user overrode a shortage
+            // that is actually there. A new sale drops a reason it didn't use,
+            // an edited one keeps its recorded reason unless it's emptied.
             SaleMessage::Submit(is_edit) => {
+                self.override_shortage =
+                    self.allow_backorder && !sale_shortages(&self.products_to_add).is_empty();
+                if !is_edit && !self.override_shortage {
+                    self.add_sales.backorder_reason = None;
+                }
+                self.allow_backorder = false;
                 if is_edit {
                     self.edit_sale = false;
+                    self.view_sale = false;
                 } else {
-                    self.products_to_add.iter_mut().for_each(|item| {
-                        self.add_sales.cost += item.cost;
-                        let total = item.msrp * item.qty as f64;
-                        self.add_sales.total += total;
-                        self.add_sales.net += total - (item.cost * item.qty as f64);
-                    });
-
-                    if self.add_sales.total >= 500.00 {
-                        self.add_sales.shipping = 0.00;
-                    } else {
-                        self.add_sales.shipping = 15.00;
-                    }
-
-                    if let Some(_) = self.add_sales.rep_id {
-                        let rep_cut =
-                            self.add_sales.total * (self.add_sales.rep_percentage as f64 / 100.00);
-                        let new_net = self.add_sales.net - rep_cut;
-                        self.add_sales.net = new_net;
-                        self.add_sales.rep_cut = Some(rep_cut);
-                    }
-
-                    self.add_sales.total += self.add_sales.shipping;
-                    self.add_sales.cost += 9.00; // 9.00 cost to ship
-                    self.add_sales.net += self.add_sales.shipping - 9.00; // 9.00 cost to ship
-
                     self.add_sale = false;
                 }
             }
+            SaleMessage::AllowBackorder(b) => {
+                self.allow_backorder = b;
+            }
+            SaleMessage::BackorderReason(r) => {
+                if r.chars().count() == 0 {
+                    self.sale_mut().backorder_reason = None;
+                } else {
+                    self.sale_mut().backorder_reason = Some(r);
+                }
+            }
             SaleMessage::Query(q) => {
                 if q.len() > 0 {
                     self.filtered_products = self
@@ -674,9 +587,6 @@ impl SalesState {
                 );
                 ctx.set_contents(contents).unwrap();
             }
-            SaleMessage::Fulfill => {
-                println!("fulfilling")
-            }
             SaleMessage::CloseSale => {
                 self.view_sale = false;
             }
@@ -790,7 +700,7 @@ impl SalesState {
                         Column::new()
                             .padding(12)
                             .width(Length::Fill)
-                            .push(Column::new().push(Text::new(self.add_sales.rep_name.clone()))),
+                            .push(Column::new().push(Text::new(self.sale().rep_name.clone()))),
                     )
                     .style(card_style()),
                 ),
@@ -805,7 +715,7 @@ impl SalesState {
                 .push(
                     Container::new(
                         Column::new().padding(12).width(Length::Fill).push(
-                            Column::new().push(Text::new(self.add_sales.client_name.clone())),
+                            Column::new().push(Text::new(self.sale().client_name.clone())),
                         ),
                     )
                     .style(card_style()),
@@ -896,6 +806,24 @@ impl SalesState {
                                                 .align_items(Alignment::Center)
                                                 .push(Text::new(product.qty.to_string())),
                                         )
+                                        .push(
+                                            TextInput::new("Discount", &product.discount_input)
+                                                .width(70)
+                                                .on_input(|input| {
+                                                    AppMessage::Sale(SaleMessage::LineDiscount(
+                                                        product.product_id,
+                                                        input,
+                                                    ))
+                                                }),
+                                        )
+                                        .push(
+                                            Checkbox::new("%", product.discount_percent).on_toggle(|b| {
+                                                AppMessage::Sale(SaleMessage::LineDiscountPercent(
+                                                    product.product_id,
+                                                    b,
+                                                ))
+                                            }),
+                                        )
                                         .push(close_button(AppMessage::Sale(
                                             SaleMessage::RemoveProduct(product.product_id),
                                         ))),
@@ -922,6 +850,12 @@ impl SalesState {
                             "Add Sale",
                             AppMessage::Sale(SaleMessage::ShowAddProducts),
                         ))
+                        .push(export_row(
+                            &self.export_path,
+                            |input| AppMessage::Sale(SaleMessage::ExportPath(input)),
+                            AppMessage::Sale(SaleMessage::Export),
+                        ))
+                        .spacing(12)
                         .padding(12),
                 )
                 .push_maybe(self.view_sale())
@@ -932,8 +866,8 @@ impl SalesState {
                         Container::new(
                             Scrollable::new(
                                 table_header(&[
-                                    "Status", "Date", "Discount", "Shipping", "Total", "Cost",
-                                    "Rep Cut", "Net", "Client", "Rep", "Note",
+                                    "Status", "Date", "Discount", "Shipping", "Total", "Balance",
+                                    "Cost", "Rep Cut", "Net", "Client", "Rep", "Note",
                                 ])
                                 .push(Scrollable::new(
                                     Column::new().padding([0, 8, 0, 0]).extend(
@@ -943,11 +877,11 @@ impl SalesState {
                                                     Column::new().push(
                                                         Row::new()
                                                             .padding(10)
-                                                            .push(table_column(&item.status))
+                                                            .push(table_column(&item.status.to_string()))
                                                             .push(table_column(&item.date))
                                                             .push(table_column(&format!(
                                                                 "${:.2}",
-                                                                &item.discount.unwrap_or(0.00)
+                                                                &item.discount_amount
                                                             )))
                                                             .push(table_column(&format!(
                                                                 "${:.2}",
@@ -957,6 +891,10 @@ impl SalesState {
                                                                 "${:.2}",
                                                                 &item.total
                                                             )))
+                                                            .push(table_column(&format!(
+                                                                "${:.2}",
+                                                                item.balance()
+                                                            )))
                                                             .push(table_column(&format!(
                                                                 "${:.2}",
                                                                 &item.cost
@@ -999,14 +937,75 @@ impl SalesState {
         .into()
     }
 
+    // Deleting a cancelled or returned sale restocks nothing, its units already
+    // went back or were written off when they were returned.
+    fn restock_confirm_view(
+        &self,
+        prompt: String,
+        confirm_label: &str,
+        confirm: SaleMessage,
+    ) -> Container<'_, AppMessage> {
+        let restocks = self.sale_to_view.status.holds_stock();
+        let written_off = self.sale_products_to_view.iter().any(|item| item.written_off > 0);
+
+        Container::new(
+            Column::new()
+                .spacing(8)
+                .push(Text::new(prompt).size(20))
+                .push(Text::new(if restocks {
+                    "These units will be put back in stock:"
+                } else if written_off {
+                    "Nothing will be restocked, the returns already put back every unit that wasn't written off."
+                } else {
+                    "This sale's units are already back in stock, nothing will be restocked."
+                }))
+                .extend(
+                    self.sale_products_to_view
+                        .iter()
+                        .filter(|_| restocks)
+                        .map(|item| {
+                            Text::new(format!(
+                                "{}: +{} ({} -> {})",
+                                item.name,
+                                item.qty - item.returned,
+                                item.units,
+                                item.units + item.qty - item.returned
+                            ))
+                            .into()
+                        }),
+                )
+                .push(
+                    Row::new()
+                        .spacing(12)
+                        .push(
+                            Button::new(Text::new(confirm_label.to_string()))
+                                .on_press(AppMessage::Sale(confirm))
+                                .style(iced::theme::Button::Destructive),
+                        )
+                        .push(
+                            Button::new("Keep")
+                                .on_press(AppMessage::Sale(SaleMessage::AbortConfirm))
+                                .style(CustomMainButtonStyle),
+                        ),
+                ),
+        )
+        .padding(12)
+        .style(card_style())
+    }
+
     fn edit_view(&self) -> Option<Element<AppMessage>> {
         if self.edit_sale {
+            let shortages = sale_shortages(&self.products_to_add);
+            let can_submit = shortages.is_empty()
+                || (self.allow_backorder && self.sale_to_edit.backorder_reason.is_some());
+
             Some(
                 Container::new(
                     Column::new()
                         .push(
                             Container::new(
                                 Column::new()
+                                    .spacing(12)
                                     .push(
                                         Text::new("Edit Sale".to_string())
                                             .size(24)
@@ -1015,7 +1014,7 @@ impl SalesState {
                                     )
                                     .push(text_input_column(
                                         "Discount",
-                                        &self.sale_to_edit.discount.unwrap_or(0.00).to_string(),
+                                        &self.sale_to_edit.discount_input,
                                         |input| {
                                             AppMessage::Sale(SaleMessage::DiscountInput(
                                                 input, true,
@@ -1023,8 +1022,14 @@ impl SalesState {
                                         },
                                         None,
                                     ))
+                                    .push(
+                                        Checkbox::new("Percent", self.sale_to_edit.discount_percent)
+                                            .on_toggle(|b| {
+                                                AppMessage::Sale(SaleMessage::DiscountPercent(b, true))
+                                            }),
+                                    )
                                     .push(text_input_column(
-                                        "Date",
+                                        "Date (YYYY-MM-DD)",
                                         &self.sale_to_edit.date,
                                         |input| {
                                             AppMessage::Sale(SaleMessage::DateInput(input, true))
@@ -1039,6 +1044,37 @@ impl SalesState {
                                         },
                                         None,
                                     ))
+                                    .push(
+                                        Row::new()
+                                            .spacing(12)
+                                            .push(self.select_product())
+                                            .push(self.selected_products()),
+                                    )
+                                    .push(
+                                        Row::new()
+                                            .spacing(12)
+                                            .push(self.select_client())
+                                            .push(self.selected_client()),
+                                    )
+                                    .push(
+                                        Row::new()
+                                            .spacing(12)
+                                            .push(self.select_rep())
+                                            .push(self.selected_rep()),
+                                    )
+                                    .push_maybe(shortage_view(
+                                        &shortages,
+                                        self.allow_backorder,
+                                        &self
+                                            .sale_to_edit
+                                            .backorder_reason
+                                            .clone()
+                                            .unwrap_or("".to_string()),
+                                        |b| AppMessage::Sale(SaleMessage::AllowBackorder(b)),
+                                        |input| {
+                                            AppMessage::Sale(SaleMessage::BackorderReason(input))
+                                        },
+                                    ))
                                     .push(
                                         Row::new()
                                             .push(
@@ -1046,9 +1082,13 @@ impl SalesState {
                                                     Text::new("Submit".to_string())
                                                         .horizontal_alignment(Horizontal::Center),
                                                 )
-                                                .on_press(AppMessage::Sale(SaleMessage::Submit(
-                                                    true,
-                                                )))
+                                                .on_press_maybe(if can_submit {
+                                                    Some(AppMessage::Sale(SaleMessage::Submit(
+                                                        true,
+                                                    )))
+                                                } else {
+                                                    None
+                                                })
                                                 .style(CustomMainButtonStyle)
                                                 .width(Length::Fill),
                                             )
@@ -1058,15 +1098,26 @@ impl SalesState {
                                                     Text::new("Delete".to_string())
                                                         .horizontal_alignment(Horizontal::Center),
                                                 )
-                                                .on_press(AppMessage::Sale(SaleMessage::Delete))
+                                                .on_press(AppMessage::Sale(
+                                                    SaleMessage::ConfirmDelete,
+                                                ))
                                                 .width(Length::Fill)
                                                 .style(iced::theme::Button::Destructive),
                                             ),
-                                    ),
+                                    )
+                                    .push_maybe(if self.confirm_delete {
+                                        Some(self.restock_confirm_view(
+                                            format!("Delete sale #{}?", self.sale_to_view.sale_id),
+                                            "Delete",
+                                            SaleMessage::Delete,
+                                        ))
+                                    } else {
+                                        None
+                                    }),
                             )
                             .padding(24),
                         )
-                        .max_width(700),
+                        .max_width(1000),
                 )
                 .align_x(Horizontal::Center)
                 .width(Length::Fill)
@@ -1079,6 +1130,10 @@ impl SalesState {
 
     pub fn create_view(&self) -> Option<Element<AppMessage>> {
         if self.add_sale {
+            let shortages = sale_shortages(&self.products_to_add);
+            let can_submit = shortages.is_empty()
+                || (self.allow_backorder && self.add_sales.backorder_reason.is_some());
+
             Some(
                 Scrollable::new(
                     Column::new()
@@ -1093,14 +1148,20 @@ impl SalesState {
                                 .align_items(Alignment::Center)
                                 .push(text_input_column(
                                     "Discount",
-                                    &self.add_sales.discount.unwrap_or(0.00).to_string(),
+                                    &self.add_sales.discount_input,
                                     |input| {
                                         AppMessage::Sale(SaleMessage::DiscountInput(input, false))
                                     },
                                     None,
                                 ))
+                                .push(
+                                    Checkbox::new("Percent", self.add_sales.discount_percent)
+                                        .on_toggle(|b| {
+                                            AppMessage::Sale(SaleMessage::DiscountPercent(b, false))
+                                        }),
+                                )
                                 .push(text_input_column(
-                                    "Date",
+                                    "Date (YYYY-MM-DD)",
                                     &self.add_sales.date,
                                     |input| AppMessage::Sale(SaleMessage::DateInput(input, false)),
                                     None,
@@ -1147,9 +1208,24 @@ impl SalesState {
                                     |input| AppMessage::Sale(SaleMessage::NoteInput(input, false)),
                                     None,
                                 ))
+                                .push_maybe(shortage_view(
+                                    &shortages,
+                                    self.allow_backorder,
+                                    &self
+                                        .add_sales
+                                        .backorder_reason
+                                        .clone()
+                                        .unwrap_or("".to_string()),
+                                    |b| AppMessage::Sale(SaleMessage::AllowBackorder(b)),
+                                    |input| AppMessage::Sale(SaleMessage::BackorderReason(input)),
+                                ))
                                 .push(
                                     Button::new("Submit")
-                                        .on_press(AppMessage::Sale(SaleMessage::Submit(false)))
+                                        .on_press_maybe(if can_submit {
+                                            Some(AppMessage::Sale(SaleMessage::Submit(false)))
+                                        } else {
+                                            None
+                                        })
                                         .style(CustomMainButtonStyle),
                                 ),
                         ),
@@ -1231,6 +1307,157 @@ impl SalesState {
         }
     }
 
+    fn payments_view(&self) -> Column<'_, AppMessage> {
+        Column::new()
+            .spacing(8)
+            .padding([12, 0, 0, 0])
+            .push(Text::new("Payments"))
+            .push(item_view_row("Total", format!("${:.2}", self.sale_to_view.total)))
+            .push(item_view_row("Paid", format!("${:.2}", self.sale_to_view.paid)))
+            .push(item_view_row("Balance", format!("${:.2}", self.sale_to_view.balance())))
+            .extend(self.payments.iter().map(|payment| {
+                Text::new(format!(
+                    "{}: ${:.2} {}{}",
+                    payment.date,
+                    payment.amount,
+                    payment.method,
+                    payment
+                        .reference
+                        .as_ref()
+                        .map(|r| format!(" ({})", r))
+                        .unwrap_or_default()
+                ))
+                .size(14)
+                .into()
+            }))
+            .push_maybe(if self.sale_to_view.status != SaleStatus::Cancelled && self.sale_to_view.balance() >= 0.005 {
+                Some(
+                    Column::new()
+                        .spacing(8)
+                        .push(text_input_column(
+                            "Date (YYYY-MM-DD)",
+                            &self.payment_to_add.date,
+                            |input| AppMessage::Sale(SaleMessage::PaymentDate(input)),
+                            None,
+                        ))
+                        .push(text_input_column(
+                            "Amount",
+                            &self.payment_to_add.amount,
+                            |input| AppMessage::Sale(SaleMessage::PaymentAmount(input)),
+                            None,
+                        ))
+                        .push(text_input_column(
+                            "Method",
+                            &self.payment_to_add.method,
+                            |input| AppMessage::Sale(SaleMessage::PaymentMethod(input)),
+                            None,
+                        ))
+                        .push(text_input_column(
+                            "Reference",
+                            &self.payment_to_add.reference,
+                            |input| AppMessage::Sale(SaleMessage::PaymentReference(input)),
+                            Some(AppMessage::Sale(SaleMessage::RecordPayment)),
+                        ))
+                        .push(
+                            Button::new("Record Payment")
+                                .on_press(AppMessage::Sale(SaleMessage::RecordPayment))
+                                .style(CustomMainButtonStyle),
+                        ),
+                )
+            } else {
+                None
+            })
+    }
+
+    // Returns are taken on sales that have been sent to the client.
+    fn returns_view(&self) -> Column<'_, AppMessage> {
+        let can_return = matches!(
+            self.sale_to_view.status,
+            SaleStatus::Shipped | SaleStatus::Completed
+        );
+
+        Column::new()
+            .spacing(8)
+            .padding([12, 0, 0, 0])
+            .push(Text::new("Returns"))
+            .push_maybe(if self.sale_to_view.refunded != 0.00 {
+                Some(item_view_row("Refunded", format!("${:.2}", self.sale_to_view.refunded)))
+            } else {
+                None
+            })
+            .extend(self.returns.iter().map(|ret| {
+                Text::new(format!(
+                    "{}: ${:.2} refunded, {}{}",
+                    ret.date,
+                    ret.refund,
+                    ret.items,
+                    ret.note
+                        .as_ref()
+                        .map(|n| format!(" ({})", n))
+                        .unwrap_or_default()
+                ))
+                .size(14)
+                .into()
+            }))
+            .push_maybe(if can_return {
+                Some(
+                    Button::new(if self.add_return { "Close Return" } else { "Record Return" })
+                        .on_press(AppMessage::Sale(SaleMessage::ShowAddReturn))
+                        .style(CustomMainButtonStyle),
+                )
+            } else {
+                None
+            })
+            .push_maybe(if can_return && self.add_return {
+                Some(
+                    Column::new()
+                        .spacing(8)
+                        .extend(self.return_to_add.lines.iter().map(|line| {
+                            let id = line.product_id;
+                            Row::new()
+                                .spacing(8)
+                                .align_items(Alignment::Center)
+                                .push(Text::new(line.name.clone()).width(100))
+                                .push(
+                                    TextInput::new("Qty", &line.qty.to_string())
+                                        .on_input(move |input| AppMessage::Sale(SaleMessage::ReturnQty(id, input)))
+                                        .width(60),
+                                )
+                                .push(
+                                    Checkbox::new("Restock", line.restock)
+                                        .on_toggle(move |b| AppMessage::Sale(SaleMessage::ReturnRestock(id, b))),
+                                )
+                                .into()
+                        }))
+                        .push(text_input_column(
+                            "Date (YYYY-MM-DD)",
+                            &self.return_to_add.date,
+                            |input| AppMessage::Sale(SaleMessage::ReturnDate(input)),
+                            None,
+                        ))
+                        .push(text_input_column(
+                            "Refund",
+                            &self.return_to_add.refund,
+                            |input| AppMessage::Sale(SaleMessage::ReturnRefund(input)),
+                            None,
+                        ))
+                        .push(text_input_column(
+                            "Note",
+                            &self.return_to_add.note,
+                            |input| AppMessage::Sale(SaleMessage::ReturnNote(input)),
+                            Some(AppMessage::Sale(SaleMessage::SubmitReturn)),
+                        ))
+                        .push(
+                            Button::new("Submit Return")
+                                .on_press(AppMessage::Sale(SaleMessage::SubmitReturn))
+                                .style(CustomMainButtonStyle),
+                        ),
+                )
+            } else {
+                None
+            })
+    }
+
     fn view_sale(&self) -> Option<Element<AppMessage>> {
         if self.view_sale {
             Some(
@@ -1243,24 +1470,39 @@ impl SalesState {
                                 AppMessage::EditSale(self.sale_to_view.clone())
                                 )
                             )
+                        .push(
+                            Row::new()
+                                .push(Text::new("Status: "))
+                                .push(Text::new(self.sale_to_view.status.to_string())),
+                        )
                         .push(
                             Row::new()
                                 .spacing(12)
-                                .push(
-                                    Column::new().push(
-                                        Row::new()
-                                            .push(Text::new("Status: "))
-                                            .push(Text::new(&self.sale_to_view.status)),
-                                    ),
-                                )
-                                .push(
-                                    Column::new().push(
-                                        Button::new("Complete")
-                                            .on_press(AppMessage::Sale(SaleMessage::Fulfill))
-                                            .style(CustomMainButtonStyle),
-                                    ),
+                                .padding([8, 0, 8, 0])
+                                .extend(
+                                    self.sale_to_view
+                                        .status
+                                        .next()
+                                        .iter()
+                                        .filter(|s| **s != SaleStatus::Returned)
+                                        .map(|s| status_button(*s).into()),
                                 ),
                         )
+                        .push_maybe(self.confirm_status.map(|s| {
+                            self.restock_confirm_view(
+                                format!("Mark sale #{} {}?", self.sale_to_view.sale_id, s),
+                                status_label(s),
+                                SaleMessage::SetStatus(s),
+                            )
+                        }))
+                        .push(Column::new().extend(self.status_history.iter().map(|change| {
+                            Text::new(match change.from_status {
+                                Some(from) => format!("{}: {} -> {}", change.changed_at, from, change.to_status),
+                                None => format!("{}: {}", change.changed_at, change.to_status),
+                            })
+                            .size(14)
+                            .into()
+                        })))
                         .push(
                             Row::new()
                                 .push(
@@ -1276,9 +1518,42 @@ impl SalesState {
                                                     .into()
                                             }),
                                         ))
+                                        .push_maybe(if self.sale_to_view.discount_amount != 0.00 {
+                                            Some(item_view_row(
+                                                "Discount",
+                                                format!("-${:.2}", self.sale_to_view.discount_amount),
+                                            ))
+                                        } else {
+                                            None
+                                        })
                                         .padding([0, 12, 0, 0]),
                                 )
                                 .push(client_view(&self.client_to_view)),
+                        )
+                        .push(self.payments_view())
+                        .push(self.returns_view())
+                        .push(
+                            Column::new()
+                                .spacing(8)
+                                .padding([12, 0, 0, 0])
+                                .push(
+                                    TextInput::new("Save to folder", &self.document_dir)
+                                        .on_input(|input| AppMessage::Sale(SaleMessage::DocumentDir(input))),
+                                )
+                                .push(
+                                    Row::new()
+                                        .spacing(12)
+                                        .push(
+                                            Button::new("Invoice")
+                                                .on_press(AppMessage::Sale(SaleMessage::WriteDocument(Document::Invoice)))
+                                                .style(CustomMainButtonStyle),
+                                        )
+                                        .push(
+                                            Button::new("Packing Slip")
+                                                .on_press(AppMessage::Sale(SaleMessage::WriteDocument(Document::PackingSlip)))
+                                                .style(CustomMainButtonStyle),
+                                        ),
+                                ),
                         ),
                 )
                     .width(Length::Fill)
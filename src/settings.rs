@@ -0,0 +1,213 @@
+use iced::{
+    alignment::Horizontal,
+    widget::{Button, Column, Container, Row, Scrollable, Text},
+    Alignment, Element, Length,
+};
+
+use crate::{
+    components::{
+        add_button, layout, table_column, table_header, table_row_style, table_style,
+        text_input_column, CustomMainButtonStyle,
+    },
+    purchase::{parse_input, validate_input},
+    repo::setting::{Setting, SettingToAdd},
+    AppMessage,
+};
+
+#[derive(Default, Clone)]
+pub struct SettingsState {
+    pub settings: Vec<Setting>,
+    add_setting: bool,
+    pub setting_to_add: SettingToAdd,
+}
+
+#[derive(Clone, Debug)]
+pub enum SettingsMessage {
+    EffectiveDateInput(String),
+    ShippingInput(String),
+    FreeShippingThresholdInput(String),
+    CarrierCostInput(String),
+    PaymentTermsInput(String),
+    ShowAddSetting,
+    Submit,
+}
+
+impl SettingsState {
+    pub fn update(&mut self, message: SettingsMessage) {
+        match message {
+            SettingsMessage::EffectiveDateInput(s) => {
+                self.setting_to_add.effective_date = s;
+            }
+            SettingsMessage::ShippingInput(s) => {
+                if validate_input(&s) {
+                    self.setting_to_add.shipping = s;
+                }
+            }
+            SettingsMessage::FreeShippingThresholdInput(s) => {
+                if validate_input(&s) {
+                    self.setting_to_add.free_shipping_threshold = s;
+                }
+            }
+            SettingsMessage::CarrierCostInput(s) => {
+                if validate_input(&s) {
+                    self.setting_to_add.carrier_cost = s;
+                }
+            }
+            SettingsMessage::PaymentTermsInput(s) => {
+                if s.chars().all(|c| c.is_ascii_digit()) {
+                    self.setting_to_add.payment_terms = s;
+                }
+            }
+            SettingsMessage::ShowAddSetting => {
+                if self.add_setting {
+                    self.add_setting = false;
+                } else {
+                    // Start from the newest version so only the changed rule
+                    // needs typing.
+                    if let Some(s) = self.settings.first() {
+                        self.setting_to_add = SettingToAdd {
+                            effective_date: String::new(),
+                            shipping: s.shipping.to_string(),
+                            free_shipping_threshold: s.free_shipping_threshold.to_string(),
+                            carrier_cost: s.carrier_cost.to_string(),
+                            payment_terms: s.payment_terms.to_string(),
+                        };
+                    }
+                    self.add_setting = true;
+                }
+            }
+            SettingsMessage::Submit => {
+                self.add_setting = false;
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, AppMessage> {
+        layout(
+            Column::new()
+                .width(Length::Fill)
+                .padding([12, 0, 0, 12])
+                .align_items(Alignment::Center)
+                .push(Text::new("Settings".to_string()).size(24))
+                .push(Text::new(
+                    "Sales keep the shipping and payment rules that were in effect when they were created.",
+                ))
+                .push(
+                    Row::new()
+                        .push(add_button(
+                            "Add Version",
+                            AppMessage::Settings(SettingsMessage::ShowAddSetting),
+                        ))
+                        .padding(12),
+                )
+                .push_maybe(self.create_view())
+                .push(
+                    Container::new(
+                        table_header(&[
+                            "Effective",
+                            "Shipping",
+                            "Free Shipping At",
+                            "Carrier Cost",
+                            "Payment Terms",
+                        ])
+                        .push(Scrollable::new(Column::new().extend(
+                            self.settings.iter().map(|setting| {
+                                Container::new(
+                                    Row::new()
+                                        .padding(10)
+                                        .push(table_column(&setting.effective_date))
+                                        .push(table_column(&format!("${:.2}", setting.shipping)))
+                                        .push(table_column(&format!(
+                                            "${:.2}",
+                                            setting.free_shipping_threshold
+                                        )))
+                                        .push(table_column(&format!(
+                                            "${:.2}",
+                                            setting.carrier_cost
+                                        )))
+                                        .push(table_column(&format!(
+                                            "{} days",
+                                            setting.payment_terms
+                                        ))),
+                                )
+                                .style(table_row_style())
+                                .into()
+                            }),
+                        ))),
+                    )
+                    .style(table_style()),
+                )
+                .into(),
+        )
+        .into()
+    }
+
+    fn create_view(&self) -> Option<Element<'_, AppMessage>> {
+        if self.add_setting {
+            Some(
+                Column::new()
+                    .max_width(1000)
+                    .push(
+                        Column::new()
+                            .spacing(12)
+                            .push(
+                                Text::new("Add Version".to_string())
+                                    .size(24)
+                                    .horizontal_alignment(Horizontal::Center)
+                                    .width(Length::Fill),
+                            )
+                            .push(text_input_column(
+                                "Effective Date (YYYY-MM-DD)",
+                                &self.setting_to_add.effective_date,
+                                |input| {
+                                    AppMessage::Settings(SettingsMessage::EffectiveDateInput(input))
+                                },
+                                None,
+                            ))
+                            .push(text_input_column(
+                                "Shipping",
+                                parse_input(&self.setting_to_add.shipping),
+                                |input| AppMessage::Settings(SettingsMessage::ShippingInput(input)),
+                                None,
+                            ))
+                            .push(text_input_column(
+                                "Free Shipping At",
+                                parse_input(&self.setting_to_add.free_shipping_threshold),
+                                |input| {
+                                    AppMessage::Settings(
+                                        SettingsMessage::FreeShippingThresholdInput(input),
+                                    )
+                                },
+                                None,
+                            ))
+                            .push(text_input_column(
+                                "Carrier Cost",
+                                parse_input(&self.setting_to_add.carrier_cost),
+                                |input| {
+                                    AppMessage::Settings(SettingsMessage::CarrierCostInput(input))
+                                },
+                                None,
+                            ))
+                            .push(text_input_column(
+                                "Payment Terms (days)",
+                                &self.setting_to_add.payment_terms,
+                                |input| {
+                                    AppMessage::Settings(SettingsMessage::PaymentTermsInput(input))
+                                },
+                                Some(AppMessage::Settings(SettingsMessage::Submit)),
+                            ))
+                            .push(
+                                Row::new().push(
+                                    Button::new("Submit")
+                                        .on_press(AppMessage::Settings(SettingsMessage::Submit))
+                                        .style(CustomMainButtonStyle),
+                                ),
+                            ),
+                    )
+                    .into(),
+            )
+        } else {
+            None
+        }
+    }
+}